@@ -0,0 +1,2 @@
+ALTER TABLE container ADD COLUMN pull_secrets TEXT NOT NULL DEFAULT '';
+ALTER TABLE container ADD COLUMN service_account TEXT NOT NULL DEFAULT '';
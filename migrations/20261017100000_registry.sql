@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS registry (
+    registry TEXT NOT NULL,
+    rate_limit INTEGER,
+    rate_limit_remaining INTEGER,
+    PRIMARY KEY(registry)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE image ADD COLUMN error_kind TEXT;
+ALTER TABLE image ADD COLUMN error_message TEXT;
+ALTER TABLE image ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
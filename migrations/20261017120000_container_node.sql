@@ -0,0 +1 @@
+ALTER TABLE container ADD COLUMN node TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+ALTER TABLE image ADD COLUMN latest_major_version TEXT;
+ALTER TABLE image ADD COLUMN latest_minor_version TEXT;
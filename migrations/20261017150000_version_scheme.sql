@@ -0,0 +1,63 @@
+ALTER TABLE container ADD COLUMN version_scheme TEXT NOT NULL DEFAULT 'semver';
+
+CREATE TABLE image_new (
+    image TEXT NOT NULL,
+    image_id TEXT NOT NULL,
+    latest_tag TEXT NOT NULL,
+    latest_version_req TEXT NOT NULL,
+    latest_version_regex TEXT NOT NULL,
+    version_scheme TEXT NOT NULL DEFAULT 'semver',
+    resolved_image_id TEXT,
+    latest_image_id TEXT,
+    version TEXT,
+    latest_version TEXT,
+    last_checked DATETIME,
+    error_kind TEXT,
+    error_message TEXT,
+    failures INTEGER NOT NULL DEFAULT 0,
+    update_kind TEXT,
+    latest_major_version TEXT,
+    latest_minor_version TEXT,
+    PRIMARY KEY(image, image_id, latest_tag, latest_version_req, latest_version_regex, version_scheme)
+);
+
+INSERT INTO image_new (
+    image,
+    image_id,
+    latest_tag,
+    latest_version_req,
+    latest_version_regex,
+    resolved_image_id,
+    latest_image_id,
+    version,
+    latest_version,
+    last_checked,
+    error_kind,
+    error_message,
+    failures,
+    update_kind,
+    latest_major_version,
+    latest_minor_version
+)
+SELECT
+    image,
+    image_id,
+    latest_tag,
+    latest_version_req,
+    latest_version_regex,
+    resolved_image_id,
+    latest_image_id,
+    version,
+    latest_version,
+    last_checked,
+    error_kind,
+    error_message,
+    failures,
+    update_kind,
+    latest_major_version,
+    latest_minor_version
+FROM image;
+
+DROP TABLE image;
+
+ALTER TABLE image_new RENAME TO image;
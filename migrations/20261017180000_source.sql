@@ -0,0 +1,4 @@
+ALTER TABLE image ADD COLUMN source TEXT;
+ALTER TABLE image ADD COLUMN revision TEXT;
+ALTER TABLE image ADD COLUMN latest_revision TEXT;
+ALTER TABLE image ADD COLUMN compare_url TEXT;
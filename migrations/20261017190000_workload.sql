@@ -0,0 +1,2 @@
+ALTER TABLE container ADD COLUMN workload_kind TEXT NOT NULL DEFAULT '';
+ALTER TABLE container ADD COLUMN workload TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE container ADD COLUMN container_type TEXT NOT NULL DEFAULT 'container';
@@ -0,0 +1,23 @@
+DROP TABLE container;
+
+CREATE TABLE container (
+    cluster TEXT NOT NULL DEFAULT '',
+    namespace TEXT NOT NULL,
+    pod TEXT NOT NULL,
+    container TEXT NOT NULL,
+    image TEXT NOT NULL,
+    image_id TEXT NOT NULL,
+    latest_tag TEXT NOT NULL,
+    latest_version_req TEXT NOT NULL,
+    latest_version_regex TEXT NOT NULL,
+    pull_secrets TEXT NOT NULL DEFAULT '',
+    service_account TEXT NOT NULL DEFAULT '',
+    node TEXT NOT NULL DEFAULT '',
+    version_scheme TEXT NOT NULL DEFAULT 'semver',
+    include_prereleases BOOLEAN NOT NULL DEFAULT FALSE,
+    flavours TEXT NOT NULL DEFAULT '',
+    workload_kind TEXT NOT NULL DEFAULT '',
+    workload TEXT NOT NULL DEFAULT '',
+    container_type TEXT NOT NULL DEFAULT 'container',
+    PRIMARY KEY(cluster, namespace, pod, container)
+);
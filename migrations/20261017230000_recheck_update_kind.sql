@@ -0,0 +1 @@
+UPDATE image SET last_checked = NULL WHERE latest_image_id IS NOT NULL AND update_kind IS NULL;
@@ -0,0 +1,94 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use kube::{
+    Client, Config,
+    config::{KubeConfigOptions, Kubeconfig},
+};
+
+use crate::settings::Settings;
+
+/// Kubernetes clients by cluster name. Without configured clusters, the default
+/// client is registered under the empty name.
+#[derive(Clone)]
+pub struct Clusters {
+    clients: HashMap<String, Client>,
+}
+
+/// Qualifies a namespaced reference (e.g. `namespace/name`) with its cluster.
+pub fn cluster_reference(cluster: &str, reference: &str) -> String {
+    if cluster.is_empty() {
+        reference.to_string()
+    } else {
+        format!("{cluster}:{reference}")
+    }
+}
+
+pub fn split_cluster(reference: &str) -> (&str, &str) {
+    reference.split_once(':').unwrap_or(("", reference))
+}
+
+impl Clusters {
+    pub async fn new(settings: &Settings) -> Result<Clusters> {
+        let mut clients = HashMap::new();
+        if settings.clusters.is_empty() {
+            clients.insert(String::new(), Client::try_default().await?);
+        }
+        for cluster in &settings.clusters {
+            let kubeconfig = match &cluster.kubeconfig {
+                Some(kubeconfig) => Kubeconfig::read_from(kubeconfig)?,
+                None => Kubeconfig::read()?,
+            };
+            let options = KubeConfigOptions {
+                context: cluster.context.clone(),
+                ..Default::default()
+            };
+            let config = Config::from_custom_kubeconfig(kubeconfig, &options).await?;
+            clients.insert(cluster.name.clone(), Client::try_from(config)?);
+        }
+        Ok(Clusters { clients })
+    }
+
+    pub fn clients(&self) -> &HashMap<String, Client> {
+        &self.clients
+    }
+
+    pub fn client(&self, cluster: &str) -> Result<Client> {
+        self.clients
+            .get(cluster)
+            .cloned()
+            .ok_or(anyhow!("Unknown cluster: {cluster}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_reference() {
+        assert_eq!(cluster_reference("", "default/pull"), "default/pull");
+        assert_eq!(
+            cluster_reference("prod", "default/pull"),
+            "prod:default/pull"
+        );
+        assert_eq!(split_cluster("default/pull"), ("", "default/pull"));
+        assert_eq!(split_cluster("prod:default/pull"), ("prod", "default/pull"));
+    }
+}
@@ -29,16 +29,32 @@ pub struct Database {
     pool: SqlitePool,
 }
 
+#[derive(Clone)]
+pub struct Workload {
+    pub kind: String,
+    pub name: String,
+}
+
 #[derive(sqlx::FromRow)]
 pub struct Container {
+    pub cluster: String,
     pub namespace: String,
     pub pod: String,
+    pub workload_kind: String,
+    pub workload: String,
     pub container: String,
+    pub container_type: String,
     pub image: String,
     pub image_id: String,
     pub latest_tag: String,
     pub latest_version_req: String,
     pub latest_version_regex: String,
+    pub version_scheme: String,
+    pub include_prereleases: bool,
+    pub flavours: String,
+    pub pull_secrets: String,
+    pub service_account: String,
+    pub node: String,
 }
 
 #[derive(Clone, sqlx::FromRow)]
@@ -51,15 +67,36 @@ pub struct Image {
     pub version: Option<String>,
     pub latest_version_req: String,
     pub latest_version_regex: String,
+    pub version_scheme: String,
+    pub include_prereleases: bool,
+    pub flavours: String,
     pub latest_version: Option<String>,
+    pub latest_major_version: Option<String>,
+    pub latest_minor_version: Option<String>,
     pub last_checked: Option<OffsetDateTime>,
+    pub error_kind: Option<String>,
+    pub error_message: Option<String>,
+    pub failures: i64,
+    pub update_kind: Option<String>,
+    pub discovered_tag: Option<String>,
+    pub source: Option<String>,
+    pub revision: Option<String>,
+    pub latest_revision: Option<String>,
+    pub compare_url: Option<String>,
+    pub pull_secrets: Option<String>,
+    pub service_accounts: Option<String>,
+    pub nodes: Option<String>,
 }
 
 #[derive(Debug, Clone, sqlx::FromRow, Serialize, Hash, PartialEq, Eq, EncodeLabelSet)]
 pub struct ImageWithContainer {
+    pub cluster: String,
     pub namespace: String,
     pub pod: String,
+    pub workload_kind: String,
+    pub workload: String,
     pub container: String,
+    pub container_type: String,
     pub image: String,
     pub image_id: String,
     pub latest_tag: String,
@@ -68,7 +105,26 @@ pub struct ImageWithContainer {
     pub version: Option<String>,
     pub latest_version_req: String,
     pub latest_version_regex: String,
+    pub version_scheme: String,
+    pub include_prereleases: bool,
+    pub flavours: String,
     pub latest_version: Option<String>,
+    pub latest_major_version: Option<String>,
+    pub latest_minor_version: Option<String>,
+    pub error_kind: Option<String>,
+    pub update_kind: Option<String>,
+    pub discovered_tag: Option<String>,
+    pub source: Option<String>,
+    pub revision: Option<String>,
+    pub latest_revision: Option<String>,
+    pub compare_url: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct RegistryRateLimit {
+    pub registry: String,
+    pub rate_limit: Option<i64>,
+    pub rate_limit_remaining: Option<i64>,
 }
 
 fn matched_version(r: &Regex, v: &Option<String>) -> Option<String> {
@@ -78,6 +134,36 @@ fn matched_version(r: &Regex, v: &Option<String>) -> Option<String> {
     })
 }
 
+fn split_references(references: &Option<String>) -> Vec<String> {
+    let mut references: Vec<String> = references
+        .iter()
+        .flat_map(|r| r.split(','))
+        .filter(|r| !r.is_empty())
+        .map(|r| r.to_string())
+        .collect();
+    references.sort();
+    references.dedup();
+    references
+}
+
+impl Image {
+    pub fn pull_secrets(&self) -> Vec<String> {
+        split_references(&self.pull_secrets)
+    }
+
+    pub fn service_accounts(&self) -> Vec<String> {
+        split_references(&self.service_accounts)
+    }
+
+    pub fn nodes(&self) -> Vec<String> {
+        split_references(&self.nodes)
+    }
+
+    pub fn flavours(&self) -> Vec<String> {
+        split_references(&Some(self.flavours.clone()))
+    }
+}
+
 impl ImageWithContainer {
     pub fn version_matches_latest_version(&self) -> Result<bool> {
         let r = Regex::new(&self.latest_version_regex)?;
@@ -87,10 +173,46 @@ impl ImageWithContainer {
     }
 }
 
+#[cfg(test)]
+impl Image {
+    pub fn for_test(image: &str, image_id: &str) -> Image {
+        Image {
+            image: image.to_string(),
+            image_id: image_id.to_string(),
+            latest_tag: "latest".to_string(),
+            resolved_image_id: None,
+            latest_image_id: None,
+            version: None,
+            latest_version_req: "*".to_string(),
+            latest_version_regex: ".*".to_string(),
+            version_scheme: "semver".to_string(),
+            include_prereleases: false,
+            flavours: String::new(),
+            latest_version: None,
+            latest_major_version: None,
+            latest_minor_version: None,
+            last_checked: None,
+            error_kind: None,
+            error_message: None,
+            failures: 0,
+            update_kind: None,
+            discovered_tag: None,
+            source: None,
+            revision: None,
+            latest_revision: None,
+            compare_url: None,
+            pull_secrets: None,
+            service_accounts: None,
+            nodes: None,
+        }
+    }
+}
+
 pub trait PodInfo {
     fn namespace(&self) -> Option<String>;
     fn name(&self) -> Option<String>;
-    fn containers(&self, settings: &Settings) -> Vec<Container>;
+    fn containers(&self, settings: &Settings, cluster: &str, workload: &Workload)
+    -> Vec<Container>;
 }
 
 impl Database {
@@ -110,8 +232,8 @@ impl Database {
         Ok(())
     }
 
-    pub async fn truncate_containers(&self) -> Result<()> {
-        sqlx::query!("DELETE FROM container")
+    pub async fn truncate_containers(&self, cluster: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM container WHERE cluster = $1", cluster)
             .execute(&self.pool)
             .await?;
         Ok(())
@@ -129,6 +251,9 @@ impl Database {
                 AND image.latest_tag = container.latest_tag
                 AND image.latest_version_req = container.latest_version_req
                 AND image.latest_version_regex = container.latest_version_regex
+                AND image.version_scheme = container.version_scheme
+                AND image.include_prereleases = container.include_prereleases
+                AND image.flavours = container.flavours
                 WHERE container.image IS NULL AND container.image_id IS NULL
             )
             "#,
@@ -138,10 +263,11 @@ impl Database {
         Ok(())
     }
 
-    pub async fn delete_pod<P: PodInfo>(&self, pod: &P) -> Result<()> {
+    pub async fn delete_pod<P: PodInfo>(&self, cluster: &str, pod: &P) -> Result<()> {
         if let (Some(namespace), Some(name)) = (pod.namespace(), pod.name()) {
             sqlx::query!(
-                "DELETE FROM container WHERE namespace = $1 AND pod = $2",
+                "DELETE FROM container WHERE cluster = $1 AND namespace = $2 AND pod = $3",
+                cluster,
                 namespace,
                 name,
             )
@@ -151,38 +277,57 @@ impl Database {
         Ok(())
     }
 
-    pub async fn replace_pod<P: PodInfo>(&self, pod: &P) -> Result<()> {
+    pub async fn replace_pod<P: PodInfo>(
+        &self,
+        cluster: &str,
+        pod: &P,
+        workload: &Workload,
+    ) -> Result<()> {
         if let (Some(namespace), Some(name)) = (pod.namespace(), pod.name()) {
             let mut tx = self.pool.begin().await?;
             sqlx::query!(
-                "DELETE FROM container WHERE namespace = $1 AND pod = $2",
+                "DELETE FROM container WHERE cluster = $1 AND namespace = $2 AND pod = $3",
+                cluster,
                 namespace,
                 name,
             )
             .execute(&mut *tx)
             .await?;
 
-            for container in pod.containers(&self.settings) {
+            for container in pod.containers(&self.settings, cluster, workload) {
                 sqlx::query!(
-                    "INSERT INTO container (namespace, pod, container, image, image_id, latest_tag, latest_version_req, latest_version_regex) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                    "INSERT INTO container (cluster, namespace, pod, workload_kind, workload, container, container_type, image, image_id, latest_tag, latest_version_req, latest_version_regex, version_scheme, include_prereleases, flavours, pull_secrets, service_account, node) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)",
+                    container.cluster,
                     container.namespace,
                     container.pod,
+                    container.workload_kind,
+                    container.workload,
                     container.container,
+                    container.container_type,
                     container.image,
                     container.image_id,
                     container.latest_tag,
                     container.latest_version_req,
                     container.latest_version_regex,
+                    container.version_scheme,
+                    container.include_prereleases,
+                    container.flavours,
+                    container.pull_secrets,
+                    container.service_account,
+                    container.node,
                 )
                 .execute(&mut *tx)
                 .await?;
                 sqlx::query!(
-                    "INSERT OR IGNORE INTO image (image, image_id, latest_tag, latest_version_req, latest_version_regex) VALUES ($1, $2, $3, $4, $5)",
+                    "INSERT OR IGNORE INTO image (image, image_id, latest_tag, latest_version_req, latest_version_regex, version_scheme, include_prereleases, flavours) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
                     container.image,
                     container.image_id,
                     container.latest_tag,
                     container.latest_version_req,
                     container.latest_version_regex,
+                    container.version_scheme,
+                    container.include_prereleases,
+                    container.flavours,
                 )
                 .execute(&mut *tx)
                 .await?;
@@ -202,23 +347,51 @@ impl Database {
                         latest_version = $2,
                         resolved_image_id = $3,
                         latest_image_id = $4,
-                        last_checked = $5
-                    WHERE image = $6
-                    AND image_id = $7
-                    AND latest_tag = $8
-                    AND latest_version_req = $9
-                    AND latest_version_regex = $10
+                        last_checked = $5,
+                        error_kind = $6,
+                        error_message = $7,
+                        failures = $8,
+                        update_kind = $9,
+                        latest_major_version = $10,
+                        latest_minor_version = $11,
+                        discovered_tag = $12,
+                        source = $13,
+                        revision = $14,
+                        latest_revision = $15,
+                        compare_url = $16
+                    WHERE image = $17
+                    AND image_id = $18
+                    AND latest_tag = $19
+                    AND latest_version_req = $20
+                    AND latest_version_regex = $21
+                    AND version_scheme = $22
+                    AND include_prereleases = $23
+                    AND flavours = $24
             "#,
             image.version,
             image.latest_version,
             image.resolved_image_id,
             image.latest_image_id,
             now,
+            image.error_kind,
+            image.error_message,
+            image.failures,
+            image.update_kind,
+            image.latest_major_version,
+            image.latest_minor_version,
+            image.discovered_tag,
+            image.source,
+            image.revision,
+            image.latest_revision,
+            image.compare_url,
             image.image,
             image.image_id,
             image.latest_tag,
             image.latest_version_req,
             image.latest_version_regex,
+            image.version_scheme,
+            image.include_prereleases,
+            image.flavours,
         )
         .execute(&self.pool)
         .await?;
@@ -238,8 +411,58 @@ impl Database {
                     version,
                     latest_version_req,
                     latest_version_regex,
+                    version_scheme,
+                    include_prereleases AS "include_prereleases: bool",
+                    flavours,
                     latest_version,
-                    last_checked
+                    latest_major_version,
+                    latest_minor_version,
+                    last_checked,
+                    error_kind,
+                    error_message,
+                    failures,
+                    update_kind,
+                    discovered_tag,
+                    source,
+                    revision,
+                    latest_revision,
+                    compare_url,
+                    (
+                        SELECT GROUP_CONCAT(container.pull_secrets)
+                        FROM container
+                        WHERE container.image = image.image
+                        AND container.image_id = image.image_id
+                        AND container.latest_tag = image.latest_tag
+                        AND container.latest_version_req = image.latest_version_req
+                        AND container.latest_version_regex = image.latest_version_regex
+                        AND container.version_scheme = image.version_scheme
+                        AND container.include_prereleases = image.include_prereleases
+                        AND container.flavours = image.flavours
+                    ) AS "pull_secrets: String",
+                    (
+                        SELECT GROUP_CONCAT(container.service_account)
+                        FROM container
+                        WHERE container.image = image.image
+                        AND container.image_id = image.image_id
+                        AND container.latest_tag = image.latest_tag
+                        AND container.latest_version_req = image.latest_version_req
+                        AND container.latest_version_regex = image.latest_version_regex
+                        AND container.version_scheme = image.version_scheme
+                        AND container.include_prereleases = image.include_prereleases
+                        AND container.flavours = image.flavours
+                    ) AS "service_accounts: String",
+                    (
+                        SELECT GROUP_CONCAT(container.node)
+                        FROM container
+                        WHERE container.image = image.image
+                        AND container.image_id = image.image_id
+                        AND container.latest_tag = image.latest_tag
+                        AND container.latest_version_req = image.latest_version_req
+                        AND container.latest_version_regex = image.latest_version_regex
+                        AND container.version_scheme = image.version_scheme
+                        AND container.include_prereleases = image.include_prereleases
+                        AND container.flavours = image.flavours
+                    ) AS "nodes: String"
                 FROM image
             "#,
         )
@@ -253,9 +476,13 @@ impl Database {
             ImageWithContainer,
             r#"
                 SELECT
+                    container.cluster,
                     container.namespace,
                     container.pod,
+                    container.workload_kind,
+                    container.workload,
                     container.container,
+                    container.container_type,
                     image.image,
                     image.image_id,
                     image.latest_tag,
@@ -264,7 +491,19 @@ impl Database {
                     image.version,
                     image.latest_version_req,
                     image.latest_version_regex,
-                    image.latest_version
+                    image.version_scheme,
+                    image.include_prereleases AS "include_prereleases: bool",
+                    image.flavours,
+                    image.latest_version,
+                    image.latest_major_version,
+                    image.latest_minor_version,
+                    image.error_kind,
+                    image.update_kind,
+                    image.discovered_tag,
+                    image.source,
+                    image.revision,
+                    image.latest_revision,
+                    image.compare_url
                 FROM container
                 JOIN image
                     ON container.image = image.image
@@ -272,10 +511,44 @@ impl Database {
                     AND container.latest_tag = image.latest_tag
                     AND container.latest_version_req = image.latest_version_req
                     AND container.latest_version_regex = image.latest_version_regex
+                    AND container.version_scheme = image.version_scheme
+                    AND container.include_prereleases = image.include_prereleases
+                    AND container.flavours = image.flavours
             "#,
         )
         .fetch_all(&self.pool)
         .await?;
         Ok(images)
     }
+
+    pub async fn update_registry_rate_limit(&self, rate_limit: &RegistryRateLimit) -> Result<()> {
+        sqlx::query!(
+            r#"
+                INSERT OR REPLACE INTO registry (registry, rate_limit, rate_limit_remaining)
+                VALUES ($1, $2, $3)
+            "#,
+            rate_limit.registry,
+            rate_limit.rate_limit,
+            rate_limit.rate_limit_remaining,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_registry_rate_limit(&self) -> Result<Vec<RegistryRateLimit>> {
+        let rate_limits = sqlx::query_as!(
+            RegistryRateLimit,
+            r#"
+                SELECT
+                    registry,
+                    rate_limit,
+                    rate_limit_remaining
+                FROM registry
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rate_limits)
+    }
 }
@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+mod cluster;
 mod database;
 mod log;
 mod observe;
@@ -24,7 +25,9 @@ mod update;
 use ::log::info;
 use anyhow::Result;
 use clap::Parser;
+use cluster::Clusters;
 use database::Database;
+use futures::future::join_all;
 use log::LogError;
 use observe::Observe;
 use serve::Serve;
@@ -46,6 +49,7 @@ async fn main() -> Result<()> {
     let settings = Settings::read(&cli.config_file)?;
     let update_delay = settings.update_delay;
     let database = Database::new(settings.clone()).await?;
+    let clusters = Clusters::new(&settings).await?;
 
     let serve_task = {
         let serve = Serve::new(settings.clone(), database.clone());
@@ -53,13 +57,27 @@ async fn main() -> Result<()> {
     };
 
     let observe_task = {
-        let observe = Observe::new(database.clone());
-        tokio::spawn(async move { observe.observe().await })
+        let observers: Vec<Observe> = clusters
+            .clients()
+            .iter()
+            .map(|(cluster, client)| {
+                Observe::new(
+                    settings.clone(),
+                    database.clone(),
+                    cluster.to_string(),
+                    client.clone(),
+                )
+            })
+            .collect();
+        tokio::spawn(async move {
+            join_all(observers.iter().map(Observe::run)).await;
+            Ok(())
+        })
     };
 
     let update_task = {
         let tick_interval = settings.tick_interval;
-        let update = Update::new(settings, database);
+        let update = Update::new(settings, database, clusters).await?;
         tokio::spawn(async move {
             let mut interval = tokio::time::interval_at(update_delay, tick_interval);
             loop {
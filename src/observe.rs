@@ -14,32 +14,77 @@
  * limitations under the License.
  */
 
+mod template;
+mod workload;
+
 use crate::{
-    database::{Container, Database, PodInfo},
+    cluster::cluster_reference,
+    database::{Container, Database, PodInfo, Workload},
     settings::{Override, Settings},
 };
 use anyhow::Result;
-use k8s_openapi::api::core::v1::Pod;
+use futures::future::try_join_all;
+use k8s_openapi::{
+    NamespaceResourceScope,
+    api::{
+        apps::v1::Deployment,
+        batch::v1::CronJob,
+        core::v1::{Namespace, Pod, PodSpec},
+    },
+};
 use kube::{
-    Api, Client,
+    Api, Client, Resource,
+    api::ListParams,
     runtime::{
         WatchStreamExt, watcher,
         watcher::{Config, Event},
     },
 };
+use log::{info, warn};
+use serde::de::DeserializeOwned;
 use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::future::pending;
 use std::pin::pin;
+use template::Template;
+use tokio::time::sleep;
 use tokio_stream::StreamExt;
+use workload::Workloads;
 
 pub struct Observe {
+    settings: Settings,
     database: Database,
+    cluster: String,
+    client: Client,
+}
+
+fn apis<K>(client: &Client, namespaces: &Option<Vec<String>>) -> Vec<Api<K>>
+where
+    K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>,
+{
+    match namespaces {
+        Some(namespaces) => namespaces
+            .iter()
+            .map(|namespace| Api::namespaced(client.clone(), namespace))
+            .collect(),
+        None => vec![Api::all(client.clone())],
+    }
+}
+
+struct PodSettings {
+    tag: String,
+    version_req: String,
+    version_regex: String,
+    version_scheme: String,
+    include_prereleases: bool,
+    flavours: String,
 }
 
 fn pod_settings(
     overrides: &Option<&Override>,
     annotations: &BTreeMap<String, String>,
     container: &str,
-) -> (String, String, String) {
+) -> PodSettings {
     let from_annotations = |t: &str| -> Option<String> {
         annotations
             .get(&format!("kube-tag-radar.mkroli.com/{container}.{t}"))
@@ -49,13 +94,130 @@ fn pod_settings(
     let tag = from_annotations("tag")
         .or(overrides.and_then(|o| o.tag.clone()))
         .unwrap_or("latest".to_string());
-    let req = from_annotations("version_req")
+    let version_req = from_annotations("version_req")
         .or(overrides.and_then(|o| o.version_req.clone()))
         .unwrap_or("*".to_string());
-    let regex = from_annotations("version_regex")
+    let version_regex = from_annotations("version_regex")
         .or(overrides.and_then(|o| o.version_regex.clone()))
         .unwrap_or(".*".to_string());
-    (tag, req, regex)
+    let version_scheme = from_annotations("version_scheme")
+        .or(overrides.and_then(|o| o.version_scheme.clone()))
+        .unwrap_or("semver".to_string());
+    let include_prereleases = from_annotations("include_prereleases")
+        .map(|v| v == "true")
+        .or(overrides.and_then(|o| o.include_prereleases))
+        .unwrap_or(false);
+    let flavours = from_annotations("flavours")
+        .or(overrides.and_then(|o| o.flavours.as_ref().map(|f| f.join(","))))
+        .unwrap_or_default();
+    PodSettings {
+        tag,
+        version_req,
+        version_regex,
+        version_scheme,
+        include_prereleases,
+        flavours,
+    }
+}
+
+/// Name, image and type of all containers of a Pod spec.
+fn spec_containers(spec: &PodSpec) -> Vec<(&str, Option<&String>, &'static str)> {
+    let containers = spec
+        .containers
+        .iter()
+        .map(|c| (c.name.as_str(), c.image.as_ref(), "container"));
+    let init_containers = spec
+        .init_containers
+        .iter()
+        .flatten()
+        .map(|c| (c.name.as_str(), c.image.as_ref(), "init"));
+    let ephemeral_containers = spec
+        .ephemeral_containers
+        .iter()
+        .flatten()
+        .map(|c| (c.name.as_str(), c.image.as_ref(), "ephemeral"));
+    containers
+        .chain(init_containers)
+        .chain(ephemeral_containers)
+        .collect()
+}
+
+/// The parts of a Pod (or Pod template) containers are read from.
+struct PodParts<'a> {
+    namespace: &'a str,
+    name: &'a str,
+    annotations: Option<&'a BTreeMap<String, String>>,
+    spec: &'a PodSpec,
+}
+
+fn pod_containers(
+    settings: &Settings,
+    cluster: &str,
+    workload: &Workload,
+    pod: PodParts<'_>,
+    image_ids: &HashMap<String, String>,
+) -> Vec<Container> {
+    let PodParts {
+        namespace,
+        name: pod_name,
+        annotations,
+        spec,
+    } = pod;
+    let annotations = match annotations {
+        Some(a) => a,
+        None => &BTreeMap::new(),
+    };
+    let pull_secrets = spec
+        .image_pull_secrets
+        .iter()
+        .flatten()
+        .map(|s| cluster_reference(cluster, &format!("{namespace}/{}", s.name)))
+        .collect::<Vec<String>>()
+        .join(",");
+    let service_account = cluster_reference(
+        cluster,
+        &format!(
+            "{namespace}/{}",
+            spec.service_account_name.as_deref().unwrap_or("default")
+        ),
+    );
+    let node = spec
+        .node_name
+        .as_ref()
+        .map(|node| cluster_reference(cluster, node))
+        .unwrap_or_default();
+
+    let mut containers = Vec::new();
+    for (name, image, container_type) in spec_containers(spec) {
+        let (Some(image), Some(image_id)) = (image, image_ids.get(name)) else {
+            continue;
+        };
+        let overrides = settings.find_override(namespace, pod_name, name);
+        let pod_settings = pod_settings(&overrides, annotations, name);
+
+        let container = Container {
+            cluster: cluster.to_string(),
+            namespace: namespace.to_string(),
+            pod: pod_name.to_string(),
+            workload_kind: workload.kind.to_string(),
+            workload: workload.name.to_string(),
+            container: name.to_string(),
+            container_type: container_type.to_string(),
+            image: image.to_string(),
+            image_id: image_id.to_string(),
+            latest_tag: pod_settings.tag,
+            latest_version_req: pod_settings.version_req,
+            latest_version_regex: pod_settings.version_regex,
+            version_scheme: pod_settings.version_scheme,
+            include_prereleases: pod_settings.include_prereleases,
+            flavours: pod_settings.flavours,
+            pull_secrets: pull_secrets.to_string(),
+            service_account: service_account.to_string(),
+            node: node.to_string(),
+        };
+        containers.push(container);
+    }
+    containers
 }
 
 impl PodInfo for Pod {
@@ -67,71 +229,192 @@ impl PodInfo for Pod {
         self.metadata.name.clone()
     }
 
-    fn containers(&self, settings: &Settings) -> Vec<Container> {
-        let mut containers = Vec::new();
-
-        let annotations = match &self.metadata.annotations {
-            Some(a) => a,
-            None => &BTreeMap::new(),
-        };
-
-        if let (Some(namespace), Some(pod_name), Some(status), Some(spec)) = (
+    fn containers(
+        &self,
+        settings: &Settings,
+        cluster: &str,
+        workload: &Workload,
+    ) -> Vec<Container> {
+        match (
             PodInfo::namespace(self),
             PodInfo::name(self),
             &self.status,
             &self.spec,
-        ) && let Some(container_statuses) = &status.container_statuses
-        {
-            let images: HashMap<String, String> = HashMap::from_iter(
-                spec.containers
-                    .iter()
-                    .filter_map(|c| c.image.clone().map(|i| (c.name.clone(), i))),
-            );
-
-            for c in container_statuses {
-                let overrides = settings.find_override(&namespace, &pod_name, &c.name);
-                let (latest_tag, latest_version_req, latest_version_regex) =
-                    pod_settings(&overrides, annotations, &c.name);
-                let image = images.get(&c.name).unwrap_or(&c.image).to_string();
-
-                let container = Container {
-                    namespace: namespace.to_string(),
-                    pod: pod_name.to_string(),
-                    container: c.name.to_string(),
-                    image,
-                    image_id: c.image_id.to_string(),
-                    latest_tag: latest_tag.to_string(),
-                    latest_version_req: latest_version_req.to_string(),
-                    latest_version_regex: latest_version_regex.to_string(),
+        ) {
+            (Some(namespace), Some(pod_name), Some(status), Some(spec)) => {
+                let image_ids: HashMap<String, String> = [
+                    &status.container_statuses,
+                    &status.init_container_statuses,
+                    &status.ephemeral_container_statuses,
+                ]
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|c| (c.name.clone(), c.image_id.clone()))
+                .collect();
+                let pod = PodParts {
+                    namespace: &namespace,
+                    name: &pod_name,
+                    annotations: self.metadata.annotations.as_ref(),
+                    spec,
                 };
-                containers.push(container);
+                pod_containers(settings, cluster, workload, pod, &image_ids)
             }
+            _ => Vec::new(),
         }
-        containers
     }
 }
 
 impl Observe {
-    pub fn new(database: Database) -> Observe {
-        Observe { database }
+    pub fn new(settings: Settings, database: Database, cluster: String, client: Client) -> Observe {
+        Observe {
+            settings,
+            database,
+            cluster,
+            client,
+        }
     }
 
-    pub async fn observe(&self) -> Result<()> {
-        let client = Client::try_default().await?;
-        let api = Api::<Pod>::all(client);
-        let mut changes = pin!(watcher(api, Config::default()).default_backoff());
-        self.database.truncate_containers().await?;
+    /// Namespaces to observe, `None` meaning all namespaces.
+    async fn namespaces(&self, client: &Client) -> Result<Option<Vec<String>>> {
+        let mut namespaces = self.settings.namespaces.clone();
+        match &self.settings.namespace_selector {
+            Some(selector) => {
+                let api = Api::<Namespace>::all(client.clone());
+                let list = api.list(&ListParams::default().labels(selector)).await?;
+                namespaces.extend(list.items.into_iter().filter_map(|n| n.metadata.name));
+            }
+            None if namespaces.is_empty() => return Ok(None),
+            None => (),
+        }
+        namespaces.sort();
+        namespaces.dedup();
+        Ok(Some(namespaces))
+    }
+
+    async fn observe_pods(&self, api: Api<Pod>, workloads: &Workloads) -> Result<()> {
+        let mut config = Config::default();
+        if let Some(label_selector) = &self.settings.label_selector {
+            config = config.labels(label_selector);
+        }
+        if let Some(field_selector) = &self.settings.field_selector {
+            config = config.fields(field_selector);
+        }
+        let mut changes = pin!(watcher(api, config).default_backoff());
         while let Some(event) = changes.try_next().await? {
             match event {
                 Event::Delete(pod) => {
-                    self.database.delete_pod(&pod).await?;
+                    self.database.delete_pod(&self.cluster, &pod).await?;
                 }
                 Event::InitApply(pod) | Event::Apply(pod) => {
-                    self.database.replace_pod(&pod).await?;
+                    let workload = workloads.workload(&pod).await;
+                    self.database
+                        .replace_pod(&self.cluster, &pod, &workload)
+                        .await?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    async fn observe_templates<K>(&self, api: Api<K>) -> Result<()>
+    where
+        K: Template + Clone + Debug + DeserializeOwned + Send + 'static,
+    {
+        let mut changes = pin!(watcher(api, Config::default()).default_backoff());
+        while let Some(event) = changes.try_next().await? {
+            match event {
+                Event::InitApply(template) | Event::Apply(template) if template.tracked() => {
+                    self.database
+                        .replace_pod(&self.cluster, &template, &template.workload())
+                        .await?;
+                }
+                Event::Delete(template) | Event::InitApply(template) | Event::Apply(template) => {
+                    self.database.delete_pod(&self.cluster, &template).await?;
                 }
                 _ => (),
             }
         }
         Ok(())
     }
+
+    /// Resolves once a namespace starts or stops matching `namespace_selector`.
+    async fn namespaces_changed(&self, namespaces: &Option<Vec<String>>) -> Result<()> {
+        let (Some(selector), Some(namespaces)) = (&self.settings.namespace_selector, namespaces)
+        else {
+            return pending().await;
+        };
+        let name = |namespace: &Namespace| namespace.metadata.name.clone().unwrap_or_default();
+        let api = Api::<Namespace>::all(self.client.clone());
+        let config = Config::default().labels(selector);
+        let mut changes = pin!(watcher(api, config).default_backoff());
+        while let Some(event) = changes.try_next().await? {
+            match event {
+                Event::InitApply(namespace) | Event::Apply(namespace)
+                    if !namespaces.contains(&name(&namespace)) =>
+                {
+                    return Ok(());
+                }
+                Event::Delete(namespace)
+                    if !self.settings.namespaces.contains(&name(&namespace)) =>
+                {
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    async fn observe_namespaces(&self, namespaces: &Option<Vec<String>>) -> Result<()> {
+        let client = self.client.clone();
+        let workloads = Workloads::new(client.clone(), self.settings.update_interval);
+        self.database.truncate_containers(&self.cluster).await?;
+        tokio::try_join!(
+            try_join_all(
+                apis::<Pod>(&client, namespaces)
+                    .into_iter()
+                    .map(|api| self.observe_pods(api, &workloads))
+            ),
+            try_join_all(
+                apis::<CronJob>(&client, namespaces)
+                    .into_iter()
+                    .map(|api| self.observe_templates(api))
+            ),
+            try_join_all(
+                apis::<Deployment>(&client, namespaces)
+                    .into_iter()
+                    .map(|api| self.observe_templates(api))
+            ),
+        )?;
+        Ok(())
+    }
+
+    async fn observe(&self) -> Result<()> {
+        loop {
+            let namespaces = self.namespaces(&self.client).await?;
+            if namespaces.as_ref().is_some_and(Vec::is_empty) {
+                // Nothing to watch until a namespace matches the selector.
+                self.database.truncate_containers(&self.cluster).await?;
+                self.namespaces_changed(&namespaces).await?;
+            } else {
+                tokio::select! {
+                    result = self.observe_namespaces(&namespaces) => return result,
+                    result = self.namespaces_changed(&namespaces) => result?,
+                }
+            }
+            info!("Selected namespaces changed. Restarting observers.");
+        }
+    }
+
+    /// Keeps observing the cluster. Failures are retried without affecting other clusters.
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.observe().await {
+                warn!("Failed to observe cluster {:?}: {e}", self.cluster);
+            }
+            sleep(self.settings.tick_interval).await;
+        }
+    }
 }
@@ -0,0 +1,115 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use k8s_openapi::api::{
+    apps::v1::Deployment,
+    batch::v1::CronJob,
+    core::v1::{PodSpec, PodTemplateSpec},
+};
+use kube::Resource;
+
+use crate::{
+    database::{Container, PodInfo, Workload},
+    settings::Settings,
+};
+
+use super::{PodParts, pod_containers, spec_containers};
+
+/// Workloads whose Pod template is tracked even if no Pods are running.
+pub trait Template: Resource<DynamicType = ()> {
+    fn pod_template(&self) -> Option<&PodTemplateSpec>;
+    fn tracked(&self) -> bool;
+
+    fn workload(&self) -> Workload {
+        Workload {
+            kind: Self::kind(&()).to_string(),
+            name: self.meta().name.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl Template for CronJob {
+    fn pod_template(&self) -> Option<&PodTemplateSpec> {
+        self.spec.job_template.spec.as_ref().map(|s| &s.template)
+    }
+
+    fn tracked(&self) -> bool {
+        true
+    }
+}
+
+impl Template for Deployment {
+    fn pod_template(&self) -> Option<&PodTemplateSpec> {
+        self.spec.as_ref().map(|s| &s.template)
+    }
+
+    fn tracked(&self) -> bool {
+        self.spec.as_ref().and_then(|s| s.replicas) == Some(0)
+    }
+}
+
+fn template_image_ids(spec: &PodSpec) -> HashMap<String, String> {
+    spec_containers(spec)
+        .into_iter()
+        .filter_map(|(name, image, _)| image.map(|i| (name.to_string(), i.to_string())))
+        .collect()
+}
+
+impl<T: Template> PodInfo for T {
+    fn namespace(&self) -> Option<String> {
+        self.meta().namespace.clone()
+    }
+
+    /// Template rows are stored as `<kind>/<name>`, which can't collide with Pod names.
+    fn name(&self) -> Option<String> {
+        self.meta()
+            .name
+            .as_ref()
+            .map(|name| format!("{}/{name}", T::kind(&()).to_lowercase()))
+    }
+
+    fn containers(
+        &self,
+        settings: &Settings,
+        cluster: &str,
+        workload: &Workload,
+    ) -> Vec<Container> {
+        match (
+            PodInfo::namespace(self),
+            PodInfo::name(self),
+            self.pod_template(),
+        ) {
+            (Some(namespace), Some(name), Some(template)) => match &template.spec {
+                Some(spec) => {
+                    let pod = PodParts {
+                        namespace: &namespace,
+                        name: &name,
+                        annotations: template
+                            .metadata
+                            .as_ref()
+                            .and_then(|m| m.annotations.as_ref()),
+                        spec,
+                    };
+                    pod_containers(settings, cluster, workload, pod, &template_image_ids(spec))
+                }
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+}
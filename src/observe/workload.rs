@@ -0,0 +1,118 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{collections::HashMap, fmt::Debug, sync::Mutex, time::Duration};
+
+use anyhow::Result;
+use k8s_openapi::{
+    NamespaceResourceScope,
+    api::{apps::v1::ReplicaSet, batch::v1::Job, core::v1::Pod},
+    apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference},
+};
+use kube::{Api, Client, Resource};
+use serde::de::DeserializeOwned;
+use tokio::time::Instant;
+
+use crate::{database::Workload, log::LogError};
+
+type Key = (String, String, String);
+
+/// Resolved workloads by namespace and owner. Entries expire after `ttl`, as every rollout or
+/// CronJob run creates a new owner.
+pub struct Workloads {
+    client: Client,
+    ttl: Duration,
+    workloads: Mutex<HashMap<Key, (Instant, Workload)>>,
+}
+
+fn controller(metadata: &ObjectMeta) -> Option<&OwnerReference> {
+    metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .find(|o| o.controller == Some(true))
+}
+
+impl Workloads {
+    pub fn new(client: Client, ttl: Duration) -> Workloads {
+        Workloads {
+            client,
+            ttl,
+            workloads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn controller<K>(&self, namespace: &str, name: &str) -> Result<Option<OwnerReference>>
+    where
+        K: Resource<Scope = NamespaceResourceScope> + Clone + Debug + DeserializeOwned,
+        K::DynamicType: Default,
+    {
+        let api = Api::<K>::namespaced(self.client.clone(), namespace);
+        let owner = api
+            .get_opt(name)
+            .await?
+            .and_then(|r| controller(r.meta()).cloned());
+        Ok(owner)
+    }
+
+    async fn resolve(&self, namespace: &str, owner: &OwnerReference) -> Result<Workload> {
+        let parent = match owner.kind.as_str() {
+            "ReplicaSet" => {
+                self.controller::<ReplicaSet>(namespace, &owner.name)
+                    .await?
+            }
+            "Job" => self.controller::<Job>(namespace, &owner.name).await?,
+            _ => None,
+        };
+        let owner = parent.as_ref().unwrap_or(owner);
+        Ok(Workload {
+            kind: owner.kind.clone(),
+            name: owner.name.clone(),
+        })
+    }
+
+    pub async fn workload(&self, pod: &Pod) -> Workload {
+        let (Some(namespace), Some(owner)) = (&pod.metadata.namespace, controller(&pod.metadata))
+        else {
+            return Workload {
+                kind: "Pod".to_string(),
+                name: pod.metadata.name.clone().unwrap_or_default(),
+            };
+        };
+        let key = (namespace.clone(), owner.kind.clone(), owner.name.clone());
+        if let Some((_, workload)) = self
+            .workloads
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            .filter(|(resolved, _)| resolved.elapsed() < self.ttl)
+        {
+            return workload.clone();
+        }
+        match self.resolve(namespace, owner).await.log_error() {
+            Some(workload) => {
+                let mut workloads = self.workloads.lock().unwrap_or_else(|e| e.into_inner());
+                workloads.retain(|_, (resolved, _)| resolved.elapsed() < self.ttl);
+                workloads.insert(key, (Instant::now(), workload.clone()));
+                workload
+            }
+            None => Workload {
+                kind: owner.kind.clone(),
+                name: owner.name.clone(),
+            },
+        }
+    }
+}
@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     database::{Database, ImageWithContainer},
@@ -29,7 +29,7 @@ use axum::{
 };
 use axum_extra::{TypedHeader, headers::ContentType};
 use prometheus_client::{
-    encoding::text::encode,
+    encoding::{EncodeLabelSet, text::encode},
     metrics::{family::Family, gauge::Gauge},
     registry::Registry,
 };
@@ -43,25 +43,65 @@ fn ignored(settings: &Settings, image: &ImageWithContainer) -> bool {
 fn update_available(image: &ImageWithContainer) -> bool {
     match image {
         ImageWithContainer {
-            resolved_image_id: Some(resolved_image_id),
-            latest_image_id: Some(latest_image_id),
+            latest_image_id: Some(_),
+            update_kind,
             ..
-        } if resolved_image_id == latest_image_id => false,
-        ImageWithContainer {
-            image_id,
-            latest_image_id: Some(latest_image_id),
-            ..
-        } if image_id == latest_image_id => false,
+        } => update_kind.is_some(),
         i @ ImageWithContainer { .. } if let Ok(true) = i.version_matches_latest_version() => false,
         _ => true,
     }
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RegistryLabels {
+    registry: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct WorkloadLabels {
+    cluster: String,
+    namespace: String,
+    workload_kind: String,
+    workload: String,
+    container: String,
+    container_type: String,
+    image: String,
+    version: Option<String>,
+    latest_version: Option<String>,
+    update_kind: Option<String>,
+    error_kind: Option<String>,
+}
+
+impl From<&ImageWithContainer> for WorkloadLabels {
+    fn from(image: &ImageWithContainer) -> Self {
+        WorkloadLabels {
+            cluster: image.cluster.clone(),
+            namespace: image.namespace.clone(),
+            workload_kind: image.workload_kind.clone(),
+            workload: image.workload.clone(),
+            container: image.container.clone(),
+            container_type: image.container_type.clone(),
+            image: image.image.clone(),
+            version: image.version.clone(),
+            latest_version: image.latest_version.clone(),
+            update_kind: image.update_kind.clone(),
+            error_kind: image.error_kind.clone(),
+        }
+    }
+}
+
+fn check_failed(image: &ImageWithContainer) -> bool {
+    image.error_kind.is_some() && image.latest_image_id.is_none() && image.latest_version.is_none()
+}
+
 pub struct ServeMetrics {
     database: Database,
     settings: Settings,
     registry: Registry,
     containers: Family<ImageWithContainer, Gauge>,
+    workloads: Family<WorkloadLabels, Gauge>,
+    rate_limit: Family<RegistryLabels, Gauge>,
+    rate_limit_remaining: Family<RegistryLabels, Gauge>,
 }
 
 impl ServeMetrics {
@@ -73,27 +113,74 @@ impl ServeMetrics {
             "Available update",
             containers.clone(),
         );
+        let workloads = Family::<WorkloadLabels, Gauge>::default();
+        registry.register(
+            "kube_tag_radar_workload",
+            "Available update per workload",
+            workloads.clone(),
+        );
+        let rate_limit = Family::<RegistryLabels, Gauge>::default();
+        registry.register(
+            "kube_tag_radar_registry_rate_limit",
+            "Registry rate limit",
+            rate_limit.clone(),
+        );
+        let rate_limit_remaining = Family::<RegistryLabels, Gauge>::default();
+        registry.register(
+            "kube_tag_radar_registry_rate_limit_remaining",
+            "Remaining registry rate limit",
+            rate_limit_remaining.clone(),
+        );
         ServeMetrics {
             database,
             settings,
             registry,
             containers,
+            workloads,
+            rate_limit,
+            rate_limit_remaining,
         }
     }
 
     async fn metrics(&self) -> std::result::Result<Response, ServeError> {
         let images = self.database.list_image_with_container().await?;
         self.containers.clear();
+        let mut workloads: HashMap<WorkloadLabels, i64> = HashMap::new();
         for image in images {
             let value = if ignored(&self.settings, &image) {
                 -1
+            } else if check_failed(&image) {
+                -2
             } else if update_available(&image) {
                 1
             } else {
                 0
             };
+            let workload = workloads.entry((&image).into()).or_insert(value);
+            *workload = (*workload).max(value);
             self.containers.get_or_create(&image).set(value);
         }
+        self.workloads.clear();
+        for (labels, value) in workloads {
+            self.workloads.get_or_create(&labels).set(value);
+        }
+
+        let rate_limits = self.database.list_registry_rate_limit().await?;
+        self.rate_limit.clear();
+        self.rate_limit_remaining.clear();
+        for rate_limit in rate_limits {
+            let labels = RegistryLabels {
+                registry: rate_limit.registry,
+            };
+            if let Some(limit) = rate_limit.rate_limit {
+                self.rate_limit.get_or_create(&labels).set(limit);
+            }
+            if let Some(remaining) = rate_limit.rate_limit_remaining {
+                self.rate_limit_remaining
+                    .get_or_create(&labels)
+                    .set(remaining);
+            }
+        }
         Ok(self.into_response())
     }
 }
@@ -15,26 +15,37 @@
  */
 
 use std::{
+    cmp::Reverse,
+    collections::HashSet,
     net::{IpAddr, Ipv6Addr, SocketAddr},
     time::Duration,
 };
 
 use crate::database::ImageWithContainer;
-use anyhow::Result;
+use anyhow::{Result, bail};
 use config::{Config, Environment, File, FileFormat};
 use regex::Regex;
 use serde::{Deserialize, Deserializer};
 use tokio::time::Instant;
 
 #[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Ignore {
-    pub namespace: String,
-    pub image: String,
+    pub namespace: Option<String>,
+    pub image: Option<String>,
+    pub container_type: Option<String>,
 }
 
 impl Ignore {
     pub fn matches(&self, container: &ImageWithContainer) -> bool {
-        self.namespace == container.namespace && self.image == container.image
+        self.namespace
+            .as_ref()
+            .is_none_or(|n| *n == container.namespace)
+            && self.image.as_ref().is_none_or(|i| *i == container.image)
+            && self
+                .container_type
+                .as_ref()
+                .is_none_or(|t| *t == container.container_type)
     }
 }
 
@@ -46,6 +57,91 @@ pub struct Override {
     pub tag: Option<String>,
     pub version_req: Option<String>,
     pub version_regex: Option<String>,
+    pub version_scheme: Option<String>,
+    pub include_prereleases: Option<bool>,
+    pub flavours: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Cluster {
+    pub name: String,
+    pub context: Option<String>,
+    pub kubeconfig: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct Proxy {
+    pub https_proxy: Option<String>,
+    pub http_proxy: Option<String>,
+    #[serde(default = "Vec::new")]
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct Registry {
+    pub host: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub password_env: Option<String>,
+    pub password_file: Option<String>,
+    pub token: Option<String>,
+    pub token_env: Option<String>,
+    pub token_file: Option<String>,
+    pub ca_file: Option<String>,
+    pub insecure_skip_verify: Option<bool>,
+    pub http: Option<bool>,
+    pub proxy: Option<Proxy>,
+}
+
+impl Registry {
+    fn specificity(&self) -> usize {
+        self.host.chars().filter(|c| *c != '*').count()
+    }
+
+    fn has_credentials(&self) -> bool {
+        [
+            &self.username,
+            &self.password,
+            &self.password_env,
+            &self.password_file,
+            &self.token,
+            &self.token_env,
+            &self.token_file,
+        ]
+        .iter()
+        .any(|c| c.is_some())
+    }
+
+    /// Fills the unset fields from a less specific entry. Credentials are taken as a whole.
+    fn or(self, other: Registry) -> Registry {
+        let credentials = if self.has_credentials() {
+            self.clone()
+        } else {
+            other.clone()
+        };
+        Registry {
+            host: self.host,
+            ca_file: self.ca_file.or(other.ca_file),
+            insecure_skip_verify: self.insecure_skip_verify.or(other.insecure_skip_verify),
+            http: self.http.or(other.http),
+            proxy: self.proxy.or(other.proxy),
+            ..credentials
+        }
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        let pattern = regex::escape(&self.host).replace(r"\*", ".*");
+        match Regex::new(&format!("^{pattern}$")) {
+            Ok(r) => r.is_match(host),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Mirror {
+    pub registry: String,
+    pub endpoints: Vec<String>,
 }
 
 #[derive(Deserialize, Clone)]
@@ -61,12 +157,36 @@ pub struct Settings {
     pub update_interval: Duration,
     #[serde(deserialize_with = "parse_duration", default = "default_tick_interval")]
     pub tick_interval: Duration,
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    #[serde(default = "default_registry_concurrency")]
+    pub registry_concurrency: usize,
+    #[serde(
+        deserialize_with = "parse_duration",
+        default = "default_rate_limit_backoff"
+    )]
+    pub rate_limit_backoff: Duration,
+    #[serde(default = "default_version_label")]
+    pub version_label: String,
     #[serde(default = "default_bind_address")]
     pub bind_address: SocketAddr,
     #[serde(default = "Vec::new")]
+    pub clusters: Vec<Cluster>,
+    #[serde(default = "Vec::new")]
+    pub namespaces: Vec<String>,
+    pub namespace_selector: Option<String>,
+    pub label_selector: Option<String>,
+    pub field_selector: Option<String>,
+    #[serde(default = "Vec::new")]
     pub ignore: Vec<Ignore>,
     #[serde(default = "Vec::new")]
     pub overrides: Vec<Override>,
+    #[serde(default = "Vec::new")]
+    pub registries: Vec<Registry>,
+    #[serde(default = "Vec::new")]
+    pub mirrors: Vec<Mirror>,
+    #[serde(default = "default_proxy")]
+    pub proxy: Option<Proxy>,
 }
 
 fn default_database() -> String {
@@ -85,6 +205,48 @@ fn default_tick_interval() -> Duration {
     Duration::from_secs(60)
 }
 
+fn default_concurrency() -> usize {
+    8
+}
+
+fn default_registry_concurrency() -> usize {
+    2
+}
+
+fn default_rate_limit_backoff() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+fn default_version_label() -> String {
+    "org.opencontainers.image.version".to_string()
+}
+
+fn default_proxy() -> Option<Proxy> {
+    let env = |name: &str| {
+        std::env::var(name.to_uppercase())
+            .or_else(|_| std::env::var(name))
+            .ok()
+    };
+    let proxy = Proxy {
+        https_proxy: env("https_proxy"),
+        http_proxy: env("http_proxy"),
+        no_proxy: env("no_proxy")
+            .iter()
+            .flat_map(|n| n.split(','))
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .collect(),
+    };
+    match proxy {
+        Proxy {
+            https_proxy: None,
+            http_proxy: None,
+            ..
+        } => None,
+        proxy => Some(proxy),
+    }
+}
+
 fn default_bind_address() -> SocketAddr {
     SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 8080)
 }
@@ -118,7 +280,26 @@ impl Settings {
             )
             .add_source(Environment::with_prefix("KTR"))
             .build()?;
-        let settings = config.try_deserialize()?;
+        let settings: Settings = config.try_deserialize()?;
+        if settings.concurrency == 0 || settings.registry_concurrency == 0 {
+            bail!("concurrency and registry_concurrency must be at least 1");
+        }
+        if settings
+            .ignore
+            .iter()
+            .any(|i| i.namespace.is_none() && i.image.is_none() && i.container_type.is_none())
+        {
+            bail!("ignore entries must set at least one of namespace, image or container_type");
+        }
+        let mut cluster_names = HashSet::new();
+        for cluster in &settings.clusters {
+            if cluster.name.contains(':') {
+                bail!("Cluster name must not contain ':': {}", cluster.name);
+            }
+            if !cluster_names.insert(&cluster.name) {
+                bail!("Duplicate cluster name: {}", cluster.name);
+            }
+        }
         Ok(settings)
     }
 
@@ -133,4 +314,65 @@ impl Settings {
                 && o.container.clone().is_none_or(|c| c == container)
         })
     }
+
+    /// Merges all entries matching the host, preferring the most specific one per setting.
+    pub fn find_registry(&self, host: &str) -> Option<Registry> {
+        let mut registries: Vec<&Registry> =
+            self.registries.iter().filter(|r| r.matches(host)).collect();
+        registries.sort_by_key(|r| Reverse(r.specificity()));
+        registries.into_iter().cloned().reduce(Registry::or)
+    }
+
+    pub fn find_proxy(&self, host: &str) -> Option<Proxy> {
+        self.find_registry(host)
+            .and_then(|r| r.proxy)
+            .or(self.proxy.clone())
+    }
+
+    pub fn find_mirror(&self, registry: &str) -> Option<&Mirror> {
+        self.mirrors.iter().find(|m| m.registry == registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(host: &str) -> Registry {
+        Registry {
+            host: host.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registry_matches() {
+        assert!(registry("ghcr.io").matches("ghcr.io"));
+        assert!(!registry("ghcr.io").matches("quay.io"));
+        assert!(registry("*.example.com").matches("harbor.example.com"));
+        assert!(!registry("*.example.com").matches("harbor.example.com:5000"));
+        assert!(registry("*.example.com*").matches("harbor.example.com:5000"));
+    }
+
+    #[test]
+    fn test_find_registry() -> Result<()> {
+        let settings: Settings = serde_json::from_str(
+            r#"{
+                "registries": [
+                    {"host": "*", "token": "token", "http": false},
+                    {"host": "registry.internal", "ca_file": "ca.pem", "http": true}
+                ]
+            }"#,
+        )?;
+        let merged = settings
+            .find_registry("registry.internal")
+            .unwrap_or_default();
+        assert_eq!(merged.token.as_deref(), Some("token"));
+        assert_eq!(merged.ca_file.as_deref(), Some("ca.pem"));
+        assert_eq!(merged.http, Some(true));
+        let merged = settings.find_registry("ghcr.io").unwrap_or_default();
+        assert_eq!(merged.ca_file, None);
+        assert_eq!(merged.http, Some(false));
+        Ok(())
+    }
 }
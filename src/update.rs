@@ -14,75 +14,233 @@
  * limitations under the License.
  */
 
+mod credentials;
+mod discovered_tag;
+mod docker_config;
+mod error_kind;
 mod image_ids;
+mod image_labels;
 mod latest_image_version;
+mod platform;
+mod rate_limit;
+mod registry;
+mod source;
+mod tag_cache;
+mod update_kind;
 mod version;
+mod version_scheme;
 
-use crate::database::{Database, Image};
+use crate::cluster::Clusters;
+use crate::database::{Database, Image, RegistryRateLimit};
 use crate::log::LogError;
 use crate::settings::Settings;
 use anyhow::Result;
+use discovered_tag::{DiscoveredTag, DiscoveryMisses};
+use error_kind::error_kind;
+use futures::{StreamExt, TryStreamExt, stream};
 use image_ids::ImageIds;
-use latest_image_version::LatestImageVersion;
-use log::info;
+use image_labels::ImageLabels;
+use latest_image_version::{LatestImageVersion, LatestVersions};
+use log::{info, warn};
+use oci_client::Reference;
+use platform::NodePlatforms;
+use rate_limit::DOCKER_HUB;
+use registry::Registry;
+use source::{ImageSource, Source};
 use time::OffsetDateTime;
+use tokio::sync::Semaphore;
+use update_kind::UpdateKind;
 use version::ImageVersion;
 
 pub struct Update {
     settings: Settings,
     database: Database,
+    registry: Registry,
+    platforms: NodePlatforms,
+    discovery_misses: DiscoveryMisses,
 }
 
 impl Update {
-    pub fn new(settings: Settings, database: Database) -> Update {
-        Update { settings, database }
+    pub async fn new(settings: Settings, database: Database, clusters: Clusters) -> Result<Update> {
+        let registry = Registry::new(settings.clone(), clusters.clone()).await?;
+        let platforms = NodePlatforms::new(clusters, settings.update_interval);
+        Ok(Update {
+            settings,
+            database,
+            registry,
+            platforms,
+            discovery_misses: DiscoveryMisses::default(),
+        })
     }
 
-    async fn update_image(&self, image: &Image) -> Image {
-        info!("Updating {}.", image.image);
-        let version = image.image_version().log_error().flatten();
-        let latest_version = image.latest_image_version().await.log_error().flatten();
-
-        let image_ids = image.image_ids(&image.latest_tag.clone()).await.log_error();
-        let image_ids = match image_ids {
-            Some((resolved_image_id, latest_image_id)) => {
-                Some((resolved_image_id, latest_image_id))
-            }
-            None => match &latest_version {
-                Some(latest_version) => image.image_ids(latest_version).await.log_error(),
-                None => None,
-            },
+    async fn check_image(&self, image: &Image) -> Result<Image> {
+        let image = &Image {
+            discovered_tag: image
+                .discovered_tag(&self.registry, &self.discovery_misses)
+                .await?,
+            ..image.clone()
+        };
+        let reference = image.image_id.parse::<Reference>()?;
+        let version = match image.image_version()? {
+            Some(tag) if image.is_version(&tag)? => Some(tag),
+            tag => self.label_version(image, &reference).await.or(tag),
         };
-        let (resolved_image_id, latest_image_id) = match image_ids {
-            None => (None, None),
-            Some((resolved_image_id, latest_image_id)) => {
-                (Some(resolved_image_id), Some(latest_image_id))
+        let LatestVersions {
+            latest_version,
+            latest_major_version,
+            latest_minor_version,
+        } = image
+            .latest_image_version(&self.registry, version.as_deref())
+            .await?;
+        let latest_version = match latest_version {
+            Some(latest_version) => Some(latest_version),
+            None => {
+                let latest = Reference::with_tag(
+                    reference.registry().to_string(),
+                    reference.repository().to_string(),
+                    image.latest_tag.clone(),
+                );
+                self.label_version(image, &latest).await
             }
         };
+        let platforms = self.platforms.platforms(image).await;
 
-        Image {
+        let (resolved_image_id, latest_image_id) = match image
+            .image_ids(&self.registry, &platforms, &image.latest_tag)
+            .await
+        {
+            Ok(image_ids) => image_ids,
+            Err(e) => match &latest_version {
+                Some(latest_version) => {
+                    image
+                        .image_ids(&self.registry, &platforms, latest_version)
+                        .await?
+                }
+                None => return Err(e),
+            },
+        };
+
+        let image = Image {
             version,
             latest_version,
-            resolved_image_id,
-            latest_image_id,
+            latest_major_version,
+            latest_minor_version,
+            resolved_image_id: Some(resolved_image_id),
+            latest_image_id: Some(latest_image_id),
+            error_kind: None,
+            error_message: None,
+            failures: 0,
             ..image.clone()
+        };
+        let update_kind = image.update_kind()?;
+        let Source {
+            source,
+            revision,
+            latest_revision,
+            compare_url,
+        } = match update_kind {
+            Some(_) => image
+                .image_source(&self.registry)
+                .await
+                .log_error()
+                .unwrap_or_default(),
+            None => Source::default(),
+        };
+        Ok(Image {
+            update_kind,
+            source,
+            revision,
+            latest_revision,
+            compare_url,
+            ..image
+        })
+    }
+
+    async fn label_version(&self, image: &Image, reference: &Reference) -> Option<String> {
+        image
+            .image_labels(&self.registry, reference)
+            .await
+            .log_error()?
+            .remove(&self.settings.version_label)
+    }
+
+    async fn update_image(&self, image: &Image, concurrency: &Semaphore) -> Option<Image> {
+        // Wait for the registry first, so images of a busy registry don't block global slots.
+        let _registry_permit = self.registry.acquire(image).await.log_error();
+        let _permit = concurrency.acquire().await.ok();
+        if self.registry.rate_limited(image) {
+            return None;
+        }
+        info!("Updating {}.", image.image);
+        let result = self.check_image(image).await;
+
+        if self.registry.rate_limited(image) {
+            info!("Deferring {} due to rate limit.", image.image);
+            return None;
+        }
+
+        match result {
+            Ok(image) => Some(image),
+            Err(e) => {
+                warn!("Failed to update {}: {e}", image.image);
+                Some(Image {
+                    error_kind: Some(error_kind(&e).to_string()),
+                    error_message: Some(e.to_string()),
+                    failures: image.failures + 1,
+                    ..image.clone()
+                })
+            }
         }
     }
 
+    async fn update_docker_hub_rate_limit(&self) -> Result<()> {
+        let rate_limit = self.registry.docker_hub_rate_limit().await?;
+        self.database
+            .update_registry_rate_limit(&RegistryRateLimit {
+                registry: DOCKER_HUB.to_string(),
+                rate_limit: rate_limit.limit,
+                rate_limit_remaining: rate_limit.remaining,
+            })
+            .await?;
+        Ok(())
+    }
+
     pub async fn update_all(&self) -> Result<()> {
         self.database.delete_unused_images().await?;
         let images = self.database.list_image().await?;
-        for image in &images {
-            match image.last_checked {
-                Some(last_checked)
-                    if (OffsetDateTime::now_utc() - last_checked)
-                        < self.settings.update_interval => {}
-                _ => {
-                    let image = self.update_image(image).await;
-                    self.database.update_image_details(&image).await?;
+        self.discovery_misses.retain(&images);
+        let due: Vec<&Image> = images
+            .iter()
+            .filter(|image| match image.last_checked {
+                Some(last_checked) => {
+                    (OffsetDateTime::now_utc() - last_checked) >= self.settings.update_interval
                 }
-            }
+                None => true,
+            })
+            .collect();
+        if due.iter().any(|image| {
+            !self.registry.rate_limited(image)
+                && image
+                    .image_id
+                    .parse::<Reference>()
+                    .is_ok_and(|r| r.registry() == DOCKER_HUB)
+        }) {
+            self.update_docker_hub_rate_limit().await.log_error();
         }
+        let concurrency = Semaphore::new(self.settings.concurrency);
+        let concurrency = &concurrency;
+        stream::iter(
+            due.into_iter()
+                .filter(|image| !self.registry.rate_limited(image)),
+        )
+        .map(Ok)
+        .try_for_each_concurrent(None, |image| async move {
+            match self.update_image(image, concurrency).await {
+                Some(image) => self.database.update_image_details(&image).await,
+                None => Ok(()),
+            }
+        })
+        .await?;
         Ok(())
     }
 }
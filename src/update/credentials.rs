@@ -0,0 +1,244 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use k8s_openapi::api::core::v1::{Secret, ServiceAccount};
+use kube::Api;
+use log::debug;
+use oci_client::{Reference, secrets::RegistryAuth};
+use tokio::time::Instant;
+
+use crate::{
+    cluster::{Clusters, cluster_reference, split_cluster},
+    database::Image,
+    log::LogError,
+    settings::Settings,
+};
+
+use super::docker_config::{DockerConfig, normalize_registry};
+
+type Cache<T> = Mutex<HashMap<String, (Instant, T)>>;
+
+pub struct Credentials {
+    settings: Settings,
+    clusters: Clusters,
+    docker_config: Option<DockerConfig>,
+    service_accounts: Cache<Vec<String>>,
+    pull_secrets: Cache<Option<Arc<DockerConfig>>>,
+    auths: Cache<RegistryAuth>,
+}
+
+fn cached<T: Clone>(cache: &Cache<T>, key: &str, ttl: Duration) -> Option<T> {
+    let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(key)
+        .filter(|(fetched, _)| fetched.elapsed() < ttl)
+        .map(|(_, value)| value.clone())
+}
+
+fn cache<T>(cache: &Cache<T>, key: &str, value: T) {
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache.insert(key.to_string(), (Instant::now(), value));
+}
+
+fn namespaced_name(reference: &str) -> Result<(&str, &str)> {
+    reference
+        .split_once('/')
+        .ok_or(anyhow!("Invalid reference: {reference}"))
+}
+
+fn secret_value(
+    value: &Option<String>,
+    env: &Option<String>,
+    file: &Option<String>,
+) -> Result<Option<String>> {
+    let value = match (value, env, file) {
+        (Some(value), _, _) => Some(value.to_string()),
+        (None, Some(env), _) => Some(std::env::var(env)?),
+        (None, None, Some(file)) => Some(std::fs::read_to_string(file)?.trim_end().to_string()),
+        (None, None, None) => None,
+    };
+    Ok(value)
+}
+
+impl Credentials {
+    pub fn new(settings: Settings, clusters: Clusters) -> Credentials {
+        let docker_config = match std::env::var("KUBERNETES_SERVICE_HOST") {
+            Ok(_) => None,
+            Err(_) => DockerConfig::load().log_error().flatten(),
+        };
+        Credentials {
+            settings,
+            clusters,
+            docker_config,
+            service_accounts: Mutex::new(HashMap::new()),
+            pull_secrets: Mutex::new(HashMap::new()),
+            auths: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn service_account_pull_secrets(&self, service_account: &str) -> Result<Vec<String>> {
+        let ttl = self.settings.update_interval;
+        if let Some(pull_secrets) = cached(&self.service_accounts, service_account, ttl) {
+            return Ok(pull_secrets);
+        }
+        let key = service_account;
+        let (cluster, service_account) = split_cluster(service_account);
+        let (namespace, name) = namespaced_name(service_account)?;
+        let api = Api::<ServiceAccount>::namespaced(self.clusters.client(cluster)?, namespace);
+        let pull_secrets = match api.get_opt(name).await? {
+            Some(service_account) => service_account
+                .image_pull_secrets
+                .iter()
+                .flatten()
+                .map(|s| cluster_reference(cluster, &format!("{namespace}/{}", s.name)))
+                .collect(),
+            None => Vec::new(),
+        };
+        cache(&self.service_accounts, key, pull_secrets.clone());
+        Ok(pull_secrets)
+    }
+
+    async fn pull_secret_config(&self, secret: &str) -> Result<Option<Arc<DockerConfig>>> {
+        let ttl = self.settings.update_interval;
+        if let Some(config) = cached(&self.pull_secrets, secret, ttl) {
+            return Ok(config);
+        }
+        let key = secret;
+        let (cluster, secret) = split_cluster(secret);
+        let (namespace, name) = namespaced_name(secret)?;
+        let api = Api::<Secret>::namespaced(self.clusters.client(cluster)?, namespace);
+        let config = match api.get_opt(name).await? {
+            Some(Secret {
+                data: Some(data), ..
+            }) => {
+                if let Some(config) = data.get(".dockerconfigjson") {
+                    Some(Arc::new(DockerConfig::parse(&config.0)?))
+                } else if let Some(config) = data.get(".dockercfg") {
+                    Some(Arc::new(DockerConfig::parse_legacy(&config.0)?))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        cache(&self.pull_secrets, key, config.clone());
+        Ok(config)
+    }
+
+    async fn pull_secret_auth(&self, secret: &str, registry: &str) -> Result<Option<RegistryAuth>> {
+        let config = self.pull_secret_config(secret).await?;
+        Ok(config.and_then(|config| config.registry_auth(registry)))
+    }
+
+    fn static_auth(&self, registry: &str) -> Result<Option<RegistryAuth>> {
+        let registry = match self.settings.find_registry(normalize_registry(registry)) {
+            Some(registry) => registry,
+            None => return Ok(None),
+        };
+        let token = secret_value(&registry.token, &registry.token_env, &registry.token_file)?;
+        let password = secret_value(
+            &registry.password,
+            &registry.password_env,
+            &registry.password_file,
+        )?;
+        let auth = match (token, &registry.username, password) {
+            (Some(token), _, _) => Some(RegistryAuth::Bearer(token)),
+            (None, Some(username), Some(password)) => {
+                Some(RegistryAuth::Basic(username.to_string(), password))
+            }
+            _ => None,
+        };
+        Ok(auth)
+    }
+
+    async fn docker_config_auth(&self, registry: &str) -> Option<RegistryAuth> {
+        let docker_config = self.docker_config.as_ref()?;
+        match docker_config.credential_helper_auth(registry).await {
+            Ok(Some(auth)) => Some(auth),
+            Ok(None) => docker_config.registry_auth(registry),
+            Err(e) => {
+                debug!("Credential helper failed for {registry}: {e}");
+                docker_config.registry_auth(registry)
+            }
+        }
+    }
+
+    pub async fn registry_auth(&self, image: &Image, reference: &Reference) -> RegistryAuth {
+        let key = format!(
+            "{}|{}|{}",
+            image.pull_secrets().join(","),
+            image.service_accounts().join(","),
+            reference.resolve_registry()
+        );
+        if let Some(auth) = cached(&self.auths, &key, self.settings.update_interval) {
+            return auth;
+        }
+        let (auth, complete) = self.resolve_registry_auth(image, reference).await;
+        if complete {
+            cache(&self.auths, &key, auth.clone());
+        }
+        auth
+    }
+
+    /// Also returns whether all lookups succeeded, i.e. whether the result may be cached.
+    async fn resolve_registry_auth(
+        &self,
+        image: &Image,
+        reference: &Reference,
+    ) -> (RegistryAuth, bool) {
+        let mut complete = true;
+        let mut pull_secrets = image.pull_secrets();
+        for service_account in image.service_accounts() {
+            match self
+                .service_account_pull_secrets(&service_account)
+                .await
+                .log_error()
+            {
+                Some(secrets) => pull_secrets.extend(secrets),
+                None => complete = false,
+            }
+        }
+        for pull_secret in pull_secrets {
+            match self
+                .pull_secret_auth(&pull_secret, reference.resolve_registry())
+                .await
+                .log_error()
+            {
+                Some(Some(auth)) => return (auth, true),
+                Some(None) => (),
+                None => complete = false,
+            }
+        }
+        (self.host_auth(reference.resolve_registry()).await, complete)
+    }
+
+    pub async fn host_auth(&self, registry: &str) -> RegistryAuth {
+        if let Some(Some(auth)) = self.static_auth(registry).log_error() {
+            return auth;
+        }
+        if let Some(auth) = self.docker_config_auth(registry).await {
+            return auth;
+        }
+        RegistryAuth::Anonymous
+    }
+}
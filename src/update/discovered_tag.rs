@@ -0,0 +1,117 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{cmp::Reverse, collections::HashSet, sync::Mutex};
+
+use anyhow::Result;
+use oci_client::Reference;
+
+use crate::{database::Image, log::LogError};
+
+use super::{
+    latest_image_version::{VersionParser, captured_version, image_tags},
+    registry::Registry,
+    version::ImageVersion,
+};
+
+/// Maximum number of tags to resolve when looking for the tag of a pinned digest.
+const DISCOVERY_LIMIT: usize = 25;
+
+async fn points_to(registry: &Registry, image: &Image, reference: &Reference, tag: &str) -> bool {
+    let tagged = Reference::with_tag(
+        reference.registry().to_string(),
+        reference.repository().to_string(),
+        tag.to_string(),
+    );
+    registry
+        .fetch_manifest_digest(image, &tagged)
+        .await
+        .log_error()
+        .is_some_and(|digest| Some(digest.as_str()) == reference.digest())
+}
+
+/// Pinned images whose digest wasn't found among the newest tags. These are not searched again.
+#[derive(Default)]
+pub struct DiscoveryMisses(Mutex<HashSet<String>>);
+
+impl DiscoveryMisses {
+    fn contains(&self, image: &Image) -> bool {
+        let misses = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        misses.contains(&image.image)
+    }
+
+    fn insert(&self, image: &Image) {
+        let mut misses = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        misses.insert(image.image.clone());
+    }
+
+    /// Forgets images that are no longer running.
+    pub fn retain(&self, images: &[Image]) {
+        let mut misses = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        misses.retain(|miss| images.iter().any(|image| image.image == *miss));
+    }
+}
+
+pub trait DiscoveredTag {
+    async fn discovered_tag(
+        &self,
+        registry: &Registry,
+        misses: &DiscoveryMisses,
+    ) -> Result<Option<String>>;
+}
+
+impl DiscoveredTag for Image {
+    async fn discovered_tag(
+        &self,
+        registry: &Registry,
+        misses: &DiscoveryMisses,
+    ) -> Result<Option<String>> {
+        let reference = self.image.parse::<Reference>()?;
+        if reference.tag().is_some() || reference.digest().is_none() {
+            return Ok(None);
+        }
+        if misses.contains(self) {
+            return Ok(self.discovered_tag.clone());
+        }
+        if let Some(tag) = &self.discovered_tag
+            && points_to(registry, self, &reference, tag).await
+        {
+            return Ok(Some(tag.clone()));
+        }
+
+        let vp = VersionParser::for_image(self)?;
+        let version_regex = self.version_regex()?;
+        let mut tags: Vec<_> = image_tags(registry, self)
+            .await?
+            .into_iter()
+            .flat_map(|tag| captured_version(&version_regex, &tag))
+            .flat_map(|(tag, v)| vp.parse_version(&v).map(|version| (tag, version)))
+            .collect();
+        tags.sort_by_key(|(_, version)| Reverse(version.clone()));
+
+        for (tag, _) in tags
+            .into_iter()
+            .filter(|(tag, _)| Some(tag) != self.discovered_tag.as_ref())
+            .take(DISCOVERY_LIMIT)
+        {
+            if points_to(registry, self, &reference, &tag).await {
+                return Ok(Some(tag));
+            }
+        }
+        misses.insert(self);
+        Ok(self.discovered_tag.clone())
+    }
+}
@@ -0,0 +1,236 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{collections::HashMap, path::PathBuf, process::Stdio, time::Duration};
+
+use anyhow::{Result, anyhow};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use oci_client::secrets::RegistryAuth;
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+const DOCKER_HUB_SERVER_URL: &str = "https://index.docker.io/v1/";
+/// Helpers may block waiting for user interaction, e.g. to unlock a keychain.
+const CREDENTIAL_HELPER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuth>,
+    creds_store: Option<String>,
+    #[serde(default)]
+    cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct DockerAuth {
+    username: Option<String>,
+    password: Option<String>,
+    auth: Option<String>,
+    registrytoken: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CredentialHelperResponse {
+    username: String,
+    secret: String,
+}
+
+pub fn normalize_registry(registry: &str) -> &str {
+    let registry = registry
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let registry = registry.split('/').next().unwrap_or(registry);
+    match registry {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io",
+        registry => registry,
+    }
+}
+
+impl DockerAuth {
+    fn registry_auth(&self) -> Option<RegistryAuth> {
+        match self {
+            DockerAuth {
+                registrytoken: Some(token),
+                ..
+            } => Some(RegistryAuth::Bearer(token.to_string())),
+            DockerAuth {
+                username: Some(username),
+                password: Some(password),
+                ..
+            } => Some(RegistryAuth::Basic(
+                username.to_string(),
+                password.to_string(),
+            )),
+            DockerAuth {
+                auth: Some(auth), ..
+            } => {
+                let auth = String::from_utf8(BASE64_STANDARD.decode(auth).ok()?).ok()?;
+                let (username, password) = auth.split_once(':')?;
+                Some(RegistryAuth::Basic(
+                    username.to_string(),
+                    password.to_string(),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl DockerConfig {
+    pub fn load() -> Result<Option<DockerConfig>> {
+        let directory = match (std::env::var("DOCKER_CONFIG"), std::env::var("HOME")) {
+            (Ok(directory), _) => PathBuf::from(directory),
+            (Err(_), Ok(home)) => PathBuf::from(home).join(".docker"),
+            _ => return Ok(None),
+        };
+        let path = directory.join("config.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config = DockerConfig::parse(&std::fs::read(path)?)?;
+        Ok(Some(config))
+    }
+
+    pub fn parse(data: &[u8]) -> Result<DockerConfig> {
+        let config = serde_json::from_slice(data)?;
+        Ok(config)
+    }
+
+    pub fn parse_legacy(data: &[u8]) -> Result<DockerConfig> {
+        let auths = serde_json::from_slice(data)?;
+        Ok(DockerConfig {
+            auths,
+            ..Default::default()
+        })
+    }
+
+    pub fn registry_auth(&self, registry: &str) -> Option<RegistryAuth> {
+        let registry = normalize_registry(registry);
+        self.auths
+            .iter()
+            .filter(|(r, _)| normalize_registry(r) == registry)
+            .find_map(|(_, a)| a.registry_auth())
+    }
+
+    fn credential_helper(&self, registry: &str) -> Option<&str> {
+        self.cred_helpers
+            .iter()
+            .find(|(r, _)| normalize_registry(r) == registry)
+            .map(|(_, h)| h.as_str())
+            .or(self.creds_store.as_deref())
+    }
+
+    pub async fn credential_helper_auth(&self, registry: &str) -> Result<Option<RegistryAuth>> {
+        let registry = normalize_registry(registry);
+        let helper = match self.credential_helper(registry) {
+            Some(helper) => helper,
+            None => return Ok(None),
+        };
+        let server_url = match registry {
+            "docker.io" => DOCKER_HUB_SERVER_URL,
+            registry => registry,
+        };
+
+        let mut child = Command::new(format!("docker-credential-{helper}"))
+            .arg("get")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let output = timeout(CREDENTIAL_HELPER_TIMEOUT, async {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(server_url.as_bytes()).await?;
+            }
+            child.wait_with_output().await
+        })
+        .await
+        .map_err(|_| anyhow!("Credential helper {helper} timed out for {registry}"))??;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stdout);
+            if message.contains("credentials not found") {
+                return Ok(None);
+            }
+            return Err(anyhow!(
+                "Credential helper {helper} failed for {registry}: {}",
+                message.trim()
+            ));
+        }
+
+        let response: CredentialHelperResponse = serde_json::from_slice(&output.stdout)?;
+        let auth = match response.username.as_str() {
+            "<token>" => None,
+            _ => Some(RegistryAuth::Basic(response.username, response.secret)),
+        };
+        Ok(auth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_auth() -> Result<()> {
+        let config = DockerConfig::parse(
+            br#"{
+                "auths": {
+                    "https://index.docker.io/v1/": { "auth": "dXNlcjpwYXNz" },
+                    "ghcr.io": { "username": "octocat", "password": "token" },
+                    "registry.example.com:5000": { "registrytoken": "bearer" }
+                }
+            }"#,
+        )?;
+        assert_eq!(
+            config.registry_auth("docker.io"),
+            Some(RegistryAuth::Basic("user".to_string(), "pass".to_string()))
+        );
+        assert_eq!(
+            config.registry_auth("ghcr.io"),
+            Some(RegistryAuth::Basic(
+                "octocat".to_string(),
+                "token".to_string()
+            ))
+        );
+        assert_eq!(
+            config.registry_auth("registry.example.com:5000"),
+            Some(RegistryAuth::Bearer("bearer".to_string()))
+        );
+        assert_eq!(config.registry_auth("quay.io"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_credential_helper() -> Result<()> {
+        let config = DockerConfig::parse(
+            br#"{
+                "auths": { "ghcr.io": {} },
+                "credsStore": "desktop",
+                "credHelpers": { "123456789012.dkr.ecr.eu-west-1.amazonaws.com": "ecr-login" }
+            }"#,
+        )?;
+        assert_eq!(config.registry_auth("ghcr.io"), None);
+        assert_eq!(config.credential_helper("ghcr.io"), Some("desktop"));
+        assert_eq!(
+            config.credential_helper("123456789012.dkr.ecr.eu-west-1.amazonaws.com"),
+            Some("ecr-login")
+        );
+        Ok(())
+    }
+}
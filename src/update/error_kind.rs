@@ -0,0 +1,54 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use oci_client::errors::{OciDistributionError, OciErrorCode};
+
+use super::{rate_limit::is_rate_limited, version_scheme::UnknownVersionScheme};
+
+fn registry_error_kind(e: &OciDistributionError) -> &'static str {
+    match e {
+        e if is_rate_limited(e) => "rate_limited",
+        OciDistributionError::UnauthorizedError { .. }
+        | OciDistributionError::AuthenticationFailure(_) => "unauthorized",
+        OciDistributionError::ImageManifestNotFoundError(_)
+        | OciDistributionError::ServerError { code: 404, .. } => "not_found",
+        OciDistributionError::RegistryError { envelope, .. } => {
+            match envelope.errors.first().map(|e| &e.code) {
+                Some(OciErrorCode::Unauthorized | OciErrorCode::Denied) => "unauthorized",
+                Some(
+                    OciErrorCode::ManifestUnknown
+                    | OciErrorCode::NameUnknown
+                    | OciErrorCode::NotFound,
+                ) => "not_found",
+                _ => "registry",
+            }
+        }
+        OciDistributionError::RequestError(_) => "network",
+        _ => "registry",
+    }
+}
+
+pub fn error_kind(e: &anyhow::Error) -> &'static str {
+    if let Some(e) = e.downcast_ref::<OciDistributionError>() {
+        registry_error_kind(e)
+    } else if e.is::<regex::Error>() || e.is::<semver::Error>() || e.is::<UnknownVersionScheme>() {
+        "invalid_configuration"
+    } else if e.is::<oci_client::ParseError>() {
+        "invalid_reference"
+    } else {
+        "unknown"
+    }
+}
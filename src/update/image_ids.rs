@@ -15,35 +15,61 @@
  */
 
 use anyhow::Result;
-use oci_client::{Reference, client::ClientConfig};
+use oci_client::{Reference, manifest::OciManifest};
 
-use crate::database::Image;
+use crate::{database::Image, log::LogError};
+
+use super::{platform::NodePlatform, registry::Registry};
 
 pub trait ImageIds {
-    async fn image_ids(&self, tag: &str) -> Result<(String, String)>;
+    async fn image_ids(
+        &self,
+        registry: &Registry,
+        platforms: &[NodePlatform],
+        tag: &str,
+    ) -> Result<(String, String)>;
 }
 
-impl ImageIds for Image {
-    async fn image_ids(&self, tag: &str) -> Result<(String, String)> {
-        let client_config = ClientConfig::default();
-        let client = oci_client::Client::new(client_config);
+async fn platform_digests(
+    registry: &Registry,
+    image: &Image,
+    reference: &Reference,
+    platforms: &[NodePlatform],
+) -> Result<Vec<String>> {
+    let (manifest, _) = registry.pull_manifest(image, reference).await?;
+    let digests = match manifest {
+        OciManifest::ImageIndex(index) => index
+            .manifests
+            .into_iter()
+            .filter(|m| {
+                platforms.is_empty()
+                    || m.platform
+                        .as_ref()
+                        .is_some_and(|p| platforms.iter().any(|np| np.matches(p)))
+            })
+            .map(|m| m.digest)
+            .collect(),
+        OciManifest::Image(_) => Vec::new(),
+    };
+    Ok(digests)
+}
 
+impl ImageIds for Image {
+    async fn image_ids(
+        &self,
+        registry: &Registry,
+        platforms: &[NodePlatform],
+        tag: &str,
+    ) -> Result<(String, String)> {
         let reference = self.image_id.parse::<Reference>()?;
         let reference_digest = match reference.digest() {
             Some(digest) => digest.to_string(),
-            None => {
-                client
-                    .fetch_manifest_digest(
-                        &reference,
-                        &oci_client::secrets::RegistryAuth::Anonymous,
-                    )
-                    .await?
-            }
+            None => registry.fetch_manifest_digest(self, &reference).await?,
         };
         let reference = Reference::with_digest(
             reference.registry().to_string(),
             reference.repository().to_string(),
-            reference_digest,
+            reference_digest.clone(),
         );
 
         let latest = Reference::with_tag(
@@ -51,9 +77,28 @@ impl ImageIds for Image {
             reference.repository().to_string(),
             tag.to_string(),
         );
-        let latest_digest = client
-            .fetch_manifest_digest(&latest, &oci_client::secrets::RegistryAuth::Anonymous)
-            .await?;
+        let mut latest_digest = registry.fetch_manifest_digest(self, &latest).await?;
+
+        if latest_digest != reference_digest {
+            let latest = Reference::with_digest(
+                reference.registry().to_string(),
+                reference.repository().to_string(),
+                latest_digest.clone(),
+            );
+            if platform_digests(registry, self, &latest, platforms)
+                .await
+                .log_error()
+                .unwrap_or_default()
+                .contains(&reference_digest)
+                || platform_digests(registry, self, &reference, platforms)
+                    .await
+                    .log_error()
+                    .unwrap_or_default()
+                    .contains(&latest_digest)
+            {
+                latest_digest = reference_digest;
+            }
+        }
 
         let latest = Reference::with_digest(
             reference.registry().to_string(),
@@ -0,0 +1,86 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use oci_client::Reference;
+
+use crate::database::Image;
+
+use super::registry::Registry;
+
+pub const SOURCE_LABEL: &str = "org.opencontainers.image.source";
+pub const REVISION_LABEL: &str = "org.opencontainers.image.revision";
+
+pub trait ImageLabels {
+    async fn image_labels(
+        &self,
+        registry: &Registry,
+        reference: &Reference,
+    ) -> Result<HashMap<String, String>>;
+}
+
+fn config_labels(config: &str) -> Result<HashMap<String, String>> {
+    let config: serde_json::Value = serde_json::from_str(config)?;
+    let labels = config["config"]["Labels"]
+        .as_object()
+        .iter()
+        .flat_map(|labels| labels.iter())
+        .filter_map(|(k, v)| v.as_str().map(|v| (k.to_string(), v.to_string())))
+        .filter(|(_, v)| !v.is_empty())
+        .collect();
+    Ok(labels)
+}
+
+impl ImageLabels for Image {
+    async fn image_labels(
+        &self,
+        registry: &Registry,
+        reference: &Reference,
+    ) -> Result<HashMap<String, String>> {
+        let config = registry.pull_config(self, reference).await?;
+        config_labels(&config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_labels() -> Result<()> {
+        let config = r#"{
+            "architecture": "amd64",
+            "os": "linux",
+            "config": {
+                "Labels": {
+                    "org.opencontainers.image.version": "1.27.2"
+                }
+            }
+        }"#;
+        let labels = config_labels(config)?;
+        assert_eq!(
+            labels
+                .get("org.opencontainers.image.version")
+                .map(String::as_str),
+            Some("1.27.2")
+        );
+        assert_eq!(labels.get("version"), None);
+        assert!(config_labels(r#"{"config": {}}"#)?.is_empty());
+        Ok(())
+    }
+}
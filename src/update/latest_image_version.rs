@@ -15,34 +15,43 @@
  */
 
 use anyhow::Result;
-use oci_client::{Reference, client::ClientConfig};
+use oci_client::Reference;
 use regex::{Match, Regex};
-use semver::{Version, VersionReq};
+use semver::{Prerelease, Version, VersionReq};
 
 use crate::database::Image;
 
-pub trait LatestImageVersion {
-    async fn latest_image_version(&self) -> Result<Option<String>>;
+use super::{
+    registry::Registry,
+    version::ImageVersion,
+    version_scheme::{ParsedVersion, VersionScheme},
+};
+
+pub struct LatestVersions {
+    pub latest_version: Option<String>,
+    pub latest_major_version: Option<String>,
+    pub latest_minor_version: Option<String>,
 }
 
-async fn image_name_tags(image: &str) -> Result<Vec<String>> {
-    let client_config = ClientConfig::default();
-    let client = oci_client::Client::new(client_config);
-    let reference = image.parse::<Reference>()?;
+pub trait LatestImageVersion {
+    async fn latest_image_version(
+        &self,
+        registry: &Registry,
+        version: Option<&str>,
+    ) -> Result<LatestVersions>;
+}
 
+async fn fetch_tags(
+    registry: &Registry,
+    image: &Image,
+    reference: &Reference,
+) -> Result<Vec<String>> {
     let mut tags = Vec::new();
     let mut last: Option<String> = None;
     loop {
-        let mut page = client
-            .list_tags(
-                &reference,
-                &oci_client::secrets::RegistryAuth::Anonymous,
-                None,
-                last.as_deref(),
-            )
-            .await
-            .map(|r| r.tags)
-            .unwrap_or(Vec::new());
+        let mut page = registry
+            .list_tags(image, reference, last.as_deref())
+            .await?;
         match page.split_last_mut() {
             Some((l, _)) if Some(l.to_string()) == last => break,
             Some((l, page)) => {
@@ -56,51 +65,155 @@ async fn image_name_tags(image: &str) -> Result<Vec<String>> {
     Ok(tags)
 }
 
-async fn image_tags(image: &Image) -> Result<Vec<String>> {
-    match image_name_tags(&image.image_id).await? {
-        v if !v.is_empty() => Ok(v),
-        _ => image_name_tags(&image.image).await,
+async fn image_name_tags(
+    registry: &Registry,
+    image: &Image,
+    reference: &Reference,
+) -> Result<Vec<String>> {
+    registry
+        .cached_tags(reference, fetch_tags(registry, image, reference))
+        .await
+}
+
+pub async fn image_tags(registry: &Registry, image: &Image) -> Result<Vec<String>> {
+    let image_id = image.image_id.parse::<Reference>()?;
+    match image_name_tags(registry, image, &image_id).await {
+        Ok(v) if !v.is_empty() => Ok(v),
+        result => {
+            let reference = image.image.parse::<Reference>()?;
+            if reference.registry() == image_id.registry()
+                && reference.repository() == image_id.repository()
+            {
+                result
+            } else {
+                image_name_tags(registry, image, &reference).await
+            }
+        }
     }
 }
 
 impl LatestImageVersion for Image {
-    async fn latest_image_version(&self) -> Result<Option<String>> {
-        let version_req = VersionReq::parse(&self.latest_version_req)?;
-        let vp = VersionParser::new(version_req.clone())?;
-        let version_regex = Regex::new(&self.latest_version_regex)?;
-
-        let latest_version = image_tags(self)
-            .await?
-            .into_iter()
-            .flat_map(|v| match version_regex.captures(&v) {
-                Some(c) if c.len() >= 2 => Some((c[0].to_string(), c[1].to_string())),
-                Some(c) if c.len() == 1 => Some((c[0].to_string(), c[0].to_string())),
-                _ => None,
-            })
-            .flat_map(|(v, s)| vp.parse(&s).map(|version| (v, version)))
+    async fn latest_image_version(
+        &self,
+        registry: &Registry,
+        version: Option<&str>,
+    ) -> Result<LatestVersions> {
+        let tags = image_tags(registry, self).await?;
+        latest_versions(self, tags, version)
+    }
+}
+
+fn latest_versions(
+    image: &Image,
+    tags: Vec<String>,
+    version: Option<&str>,
+) -> Result<LatestVersions> {
+    let vp = VersionParser::for_image(image)?;
+    let version_regex = image.version_regex()?;
+    let current = version
+        .and_then(|v| captured_version(&version_regex, v))
+        .map(|(_, v)| v);
+    let flavour = current.as_deref().and_then(|v| vp.flavour(v));
+    let current = current
+        .and_then(|v| vp.parse_version(&v))
+        .and_then(|v| v.semver());
+
+    let versions: Vec<(String, ParsedVersion)> = tags
+        .into_iter()
+        .flat_map(|v| captured_version(&version_regex, &v))
+        .filter(|(_, s)| vp.flavour(s) == flavour)
+        .flat_map(|(v, s)| vp.parse(&s).map(|version| (v, version)))
+        .collect();
+    let latest = |f: &dyn Fn(&Version) -> bool| {
+        versions
+            .iter()
+            .filter(|(_, version)| version.semver().is_some_and(|v| f(&v)))
             .max_by(|(_, a), (_, b)| a.cmp(b))
-            .map(|(v, _)| v.to_string());
-        Ok(latest_version)
+            .map(|(v, _)| v.to_string())
+    };
+
+    Ok(LatestVersions {
+        latest_version: versions
+            .iter()
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(v, _)| v.to_string()),
+        latest_major_version: current
+            .as_ref()
+            .and_then(|c| latest(&|v| v.major == c.major)),
+        latest_minor_version: current
+            .as_ref()
+            .and_then(|c| latest(&|v| v.major == c.major && v.minor == c.minor)),
+    })
+}
+
+pub fn captured_version(version_regex: &Regex, tag: &str) -> Option<(String, String)> {
+    match version_regex.captures(tag) {
+        Some(c) if c.len() >= 2 => Some((c[0].to_string(), c[1].to_string())),
+        Some(c) if c.len() == 1 => Some((c[0].to_string(), c[0].to_string())),
+        _ => None,
     }
 }
 
-struct VersionParser {
+pub struct VersionParser {
+    version_scheme: VersionScheme,
     version_formatter_regex: Regex,
     version_req: VersionReq,
+    include_prereleases: bool,
+    flavours: Vec<Regex>,
 }
 
 const VERSION_REGEX: &str = r#"^[vV]?(?<major>0|[0-9]\d*)(?:\.0*(?<minor>0|[0-9]\d*))?(?:\.0*(?<patch>0|[0-9]\d*))?(?<suffix>.*)$"#;
+const CALVER_REGEX: &str = r#"^[vV]?\d+(?:[.-]\d+)*$"#;
+const DATE_REGEX: &str = r#"(?<year>\d{4})[-.]?(?<month>\d{2})[-.]?(?<day>\d{2})(?:[T_.-]?(?<hour>\d{2})[-:.]?(?<minute>\d{2})[-:.]?(?<second>\d{2}))?"#;
+const NUMERIC_REGEX: &str = r#"\d+"#;
 
 impl VersionParser {
-    fn new(version_req: VersionReq) -> Result<Self> {
-        let version_formatter_regex = Regex::new(VERSION_REGEX)?;
+    pub fn new(version_scheme: VersionScheme, version_req: VersionReq) -> Result<Self> {
+        let version_formatter_regex = Regex::new(match version_scheme {
+            VersionScheme::Semver => VERSION_REGEX,
+            VersionScheme::Calver => CALVER_REGEX,
+            VersionScheme::Date => DATE_REGEX,
+            VersionScheme::Numeric => NUMERIC_REGEX,
+            VersionScheme::Lexical => ".*",
+        })?;
         let vp = VersionParser {
+            version_scheme,
             version_formatter_regex,
             version_req,
+            include_prereleases: false,
+            flavours: Vec::new(),
         };
         Ok(vp)
     }
 
+    pub fn for_image(image: &Image) -> Result<Self> {
+        let version_req = VersionReq::parse(&image.latest_version_req)?;
+        let version_scheme = image.version_scheme.parse::<VersionScheme>()?;
+        let flavours = image
+            .flavours()
+            .iter()
+            .map(|f| Regex::new(&format!("-(?:{f})$")))
+            .collect::<Result<Vec<Regex>, _>>()?;
+        Ok(VersionParser {
+            include_prereleases: image.include_prereleases,
+            flavours,
+            ..VersionParser::new(version_scheme, version_req)?
+        })
+    }
+
+    /// Index of the flavour declared for the given version, e.g. `alpine` for `1.27-alpine`.
+    pub fn flavour(&self, version: &str) -> Option<usize> {
+        self.flavours.iter().position(|f| f.is_match(version))
+    }
+
+    fn without_flavour<'a>(&self, version: &'a str) -> &'a str {
+        self.flavours
+            .iter()
+            .find_map(|f| f.find(version))
+            .map(|m| &version[..m.start()])
+            .unwrap_or(version)
+    }
+
     fn best_effort_version(
         major: Option<&str>,
         minor: Option<&str>,
@@ -117,7 +230,7 @@ impl VersionParser {
         format!("{major}.{minor}.{patch}{suffix}")
     }
 
-    fn parse(&self, version: &str) -> Option<Version> {
+    fn parse_semver(&self, version: &str) -> Option<ParsedVersion> {
         fn as_str(c: Match<'_>) -> &str {
             c.as_str()
         }
@@ -128,14 +241,68 @@ impl VersionParser {
                 let patch = caps.name("patch").map(as_str);
                 let suffix = caps.name("suffix").map(as_str);
                 let version = VersionParser::best_effort_version(major, minor, patch, suffix);
-                match Version::parse(&version) {
-                    Ok(v) if self.version_req.matches(&v) => Some(v),
-                    _ => None,
-                }
+                Version::parse(&version).ok().map(ParsedVersion::Semver)
             }
             None => None,
         }
     }
+
+    fn parse_calver(&self, version: &str) -> Option<ParsedVersion> {
+        if !self.version_formatter_regex.is_match(version) {
+            return None;
+        }
+        version
+            .trim_start_matches(['v', 'V'])
+            .split(['.', '-'])
+            .map(|c| c.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()
+            .map(ParsedVersion::Components)
+    }
+
+    fn parse_date(&self, version: &str) -> Option<ParsedVersion> {
+        let caps = self.version_formatter_regex.captures(version)?;
+        let components = ["year", "month", "day", "hour", "minute", "second"]
+            .iter()
+            .map_while(|name| caps.name(name))
+            .map(|c| c.as_str().parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        match components.as_slice() {
+            [_, month, day, ..] if (1..=12).contains(month) && (1..=31).contains(day) => {
+                Some(ParsedVersion::Components(components))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_numeric(&self, version: &str) -> Option<ParsedVersion> {
+        self.version_formatter_regex
+            .find(version)
+            .and_then(|n| n.as_str().parse::<u64>().ok())
+            .map(|n| ParsedVersion::Components(vec![n]))
+    }
+
+    pub fn parse_version(&self, version: &str) -> Option<ParsedVersion> {
+        let version = self.without_flavour(version);
+        match self.version_scheme {
+            VersionScheme::Semver => self.parse_semver(version),
+            VersionScheme::Calver => self.parse_calver(version),
+            VersionScheme::Date => self.parse_date(version),
+            VersionScheme::Numeric => self.parse_numeric(version),
+            VersionScheme::Lexical => Some(ParsedVersion::Lexical(version.to_string())),
+        }
+    }
+
+    pub fn parse(&self, version: &str) -> Option<ParsedVersion> {
+        self.parse_version(version).filter(|v| match v.semver() {
+            Some(v) if v.pre.is_empty() => self.version_req.matches(&v),
+            Some(v) if self.include_prereleases => self.version_req.matches(&Version {
+                pre: Prerelease::EMPTY,
+                ..v
+            }),
+            Some(_) => false,
+            None => true,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -144,7 +311,7 @@ mod tests {
 
     #[test]
     fn test_best_effort_version_order() -> Result<()> {
-        let vp = VersionParser::new(VersionReq::parse("*")?)?;
+        let vp = VersionParser::new(VersionScheme::Semver, VersionReq::parse("*")?)?;
         let versions = vec![
             vp.parse("test"),
             vp.parse("2"),
@@ -157,4 +324,69 @@ mod tests {
         assert!(versions.is_sorted());
         Ok(())
     }
+
+    #[test]
+    fn test_latest_versions() -> Result<()> {
+        let image = Image::for_test("nginx:1.24.0", "docker.io/library/nginx@sha256:1");
+        let tags = ["1.24.0", "1.24.3", "1.25.1", "2.0.0", "latest"]
+            .map(String::from)
+            .to_vec();
+        let latest = latest_versions(&image, tags, Some("1.24.0"))?;
+        assert_eq!(latest.latest_version.as_deref(), Some("2.0.0"));
+        assert_eq!(latest.latest_major_version.as_deref(), Some("1.25.1"));
+        assert_eq!(latest.latest_minor_version.as_deref(), Some("1.24.3"));
+
+        let tags = [
+            "1.24.0-alpine",
+            "1.25.0-alpine",
+            "1.25.1",
+            "1.26.0-rc1-alpine",
+        ]
+        .map(String::from)
+        .to_vec();
+        let flavoured = Image {
+            flavours: "alpine".to_string(),
+            ..image.clone()
+        };
+        let latest = latest_versions(&flavoured, tags.clone(), Some("1.24.0-alpine"))?;
+        assert_eq!(latest.latest_version.as_deref(), Some("1.25.0-alpine"));
+        let prereleases = Image {
+            include_prereleases: true,
+            ..flavoured
+        };
+        let latest = latest_versions(&prereleases, tags, Some("1.24.0-alpine"))?;
+        assert_eq!(latest.latest_version.as_deref(), Some("1.26.0-rc1-alpine"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_schemes() -> Result<()> {
+        let parse = |scheme, versions: &[&str]| -> Result<Vec<Option<ParsedVersion>>> {
+            let vp = VersionParser::new(scheme, VersionReq::STAR)?;
+            Ok(versions.iter().map(|v| vp.parse(v)).collect())
+        };
+        let calver = parse(
+            VersionScheme::Calver,
+            &["2024.9.30", "2024.10.03", "2024.10.03.1"],
+        )?;
+        assert!(calver.iter().all(Option::is_some) && calver.is_sorted());
+        let date = parse(
+            VersionScheme::Date,
+            &[
+                "RELEASE.2024-10-02T08-27-28Z",
+                "RELEASE.2024-10-02T10-00-00Z",
+                "20241003-abcdef",
+            ],
+        )?;
+        assert!(date.iter().all(Option::is_some) && date.is_sorted());
+        let numeric = parse(
+            VersionScheme::Numeric,
+            &["build-9", "build-10", "build-100"],
+        )?;
+        assert!(numeric.iter().all(Option::is_some) && numeric.is_sorted());
+        let lexical = parse(VersionScheme::Lexical, &["a", "b", "c"])?;
+        assert!(lexical.is_sorted());
+        assert_eq!(parse(VersionScheme::Date, &["latest"])?, vec![None]);
+        Ok(())
+    }
 }
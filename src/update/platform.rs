@@ -0,0 +1,139 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use anyhow::Result;
+use k8s_openapi::api::core::v1::Node;
+use kube::Api;
+use oci_client::manifest::Platform;
+use tokio::time::Instant;
+
+use crate::{
+    cluster::{Clusters, split_cluster},
+    database::Image,
+    log::LogError,
+};
+
+const VARIANT_LABEL: &str = "kube-tag-radar.mkroli.com/variant";
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct NodePlatform {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+}
+
+impl NodePlatform {
+    pub fn matches(&self, platform: &Platform) -> bool {
+        platform.os.to_string() == self.os
+            && platform.architecture.to_string() == self.architecture
+            && self
+                .variant
+                .as_ref()
+                .is_none_or(|v| platform.variant.as_ref() == Some(v))
+    }
+}
+
+pub struct NodePlatforms {
+    clusters: Clusters,
+    ttl: Duration,
+    platforms: Mutex<HashMap<String, (Instant, Option<NodePlatform>)>>,
+}
+
+impl NodePlatforms {
+    pub fn new(clusters: Clusters, ttl: Duration) -> NodePlatforms {
+        NodePlatforms {
+            clusters,
+            ttl,
+            platforms: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn fetch_node_platform(&self, node: &str) -> Result<Option<NodePlatform>> {
+        let (cluster, name) = split_cluster(node);
+        let api = Api::<Node>::all(self.clusters.client(cluster)?);
+        let node_resource = match api.get_opt(name).await? {
+            Some(node_resource) => node_resource,
+            None => return Ok(None),
+        };
+        // Nodes don't report the CPU variant (e.g. arm/v7), so it can be given as a label.
+        let variant = node_resource
+            .metadata
+            .labels
+            .and_then(|mut l| l.remove(VARIANT_LABEL));
+        let node_info = node_resource.status.and_then(|s| s.node_info);
+        Ok(node_info.map(|i| NodePlatform {
+            os: i.operating_system,
+            architecture: i.architecture,
+            variant,
+        }))
+    }
+
+    /// Failures (e.g. missing permissions to get nodes) are cached as well, so they are logged
+    /// at most once per `ttl`.
+    async fn node_platform(&self, node: &str) -> Option<NodePlatform> {
+        if let Some((_, platform)) = self
+            .platforms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(node)
+            .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+        {
+            return platform.clone();
+        }
+        let platform = self.fetch_node_platform(node).await.log_error().flatten();
+        let mut platforms = self.platforms.lock().unwrap_or_else(|e| e.into_inner());
+        platforms.retain(|_, (fetched, _)| fetched.elapsed() < self.ttl);
+        platforms.insert(node.to_string(), (Instant::now(), platform.clone()));
+        platform
+    }
+
+    pub async fn platforms(&self, image: &Image) -> Vec<NodePlatform> {
+        let mut platforms = Vec::new();
+        for node in image.nodes() {
+            if let Some(platform) = self.node_platform(&node).await
+                && !platforms.contains(&platform)
+            {
+                platforms.push(platform);
+            }
+        }
+        platforms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_platform(variant: Option<&str>) -> NodePlatform {
+        NodePlatform {
+            os: "linux".to_string(),
+            architecture: "arm".to_string(),
+            variant: variant.map(|v| v.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_matches_variant() -> Result<()> {
+        let platform: Platform =
+            serde_json::from_str(r#"{"os": "linux", "architecture": "arm", "variant": "v7"}"#)?;
+        assert!(node_platform(None).matches(&platform));
+        assert!(node_platform(Some("v7")).matches(&platform));
+        assert!(!node_platform(Some("v6")).matches(&platform));
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::Result;
+use oci_client::{
+    errors::{OciDistributionError, OciErrorCode},
+    secrets::RegistryAuth,
+};
+use reqwest::{Client, RequestBuilder, header::HeaderMap};
+use serde::Deserialize;
+
+pub const DOCKER_HUB: &str = "docker.io";
+
+const DOCKER_HUB_TOKEN_URL: &str = "https://auth.docker.io/token?service=registry.docker.io&scope=repository:ratelimitpreview/test:pull";
+const DOCKER_HUB_RATE_LIMIT_URL: &str =
+    "https://registry-1.docker.io/v2/ratelimitpreview/test/manifests/latest";
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: String,
+}
+
+pub struct RateLimit {
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+}
+
+pub fn is_rate_limited(e: &OciDistributionError) -> bool {
+    match e {
+        OciDistributionError::ServerError { code: 429, .. } => true,
+        OciDistributionError::RegistryError { envelope, .. } => envelope
+            .errors
+            .iter()
+            .any(|e| e.code == OciErrorCode::Toomanyrequests),
+        _ => false,
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .split(';')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn authenticate(request: RequestBuilder, auth: &RegistryAuth) -> RequestBuilder {
+    match auth {
+        RegistryAuth::Anonymous => request,
+        RegistryAuth::Basic(username, password) => request.basic_auth(username, Some(password)),
+        RegistryAuth::Bearer(token) => request.bearer_auth(token),
+    }
+}
+
+pub async fn docker_hub_rate_limit(client: &Client, auth: &RegistryAuth) -> Result<RateLimit> {
+    let token: TokenResponse = authenticate(client.get(DOCKER_HUB_TOKEN_URL), auth)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let response = client
+        .head(DOCKER_HUB_RATE_LIMIT_URL)
+        .bearer_auth(token.token)
+        .send()
+        .await?;
+    let headers = response.headers();
+    Ok(RateLimit {
+        limit: header_value(headers, "ratelimit-limit"),
+        remaining: header_value(headers, "ratelimit-remaining"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_header_value() {
+        let mut headers = HeaderMap::new();
+        headers.insert("ratelimit-limit", HeaderValue::from_static("100;w=21600"));
+        headers.insert("ratelimit-remaining", HeaderValue::from_static("76"));
+        assert_eq!(header_value(&headers, "ratelimit-limit"), Some(100));
+        assert_eq!(header_value(&headers, "ratelimit-remaining"), Some(76));
+        assert_eq!(header_value(&headers, "ratelimit-reset"), None);
+    }
+}
@@ -0,0 +1,295 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Result, anyhow};
+use log::{debug, warn};
+use oci_client::{
+    Client, Reference,
+    client::{Certificate, CertificateEncoding, ClientConfig, ClientProtocol},
+    manifest::OciManifest,
+    secrets::RegistryAuth,
+};
+use reqwest::NoProxy;
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+use crate::{cluster::Clusters, database::Image, settings::Settings};
+
+use super::{
+    credentials::Credentials,
+    docker_config::normalize_registry,
+    rate_limit::{self, DOCKER_HUB, RateLimit, is_rate_limited},
+    tag_cache::TagCache,
+};
+
+pub struct Registry {
+    settings: Settings,
+    credentials: Credentials,
+    http: reqwest::Client,
+    clients: Mutex<HashMap<String, Client>>,
+    limits: Mutex<HashMap<String, Arc<Semaphore>>>,
+    backoff: Mutex<HashMap<String, Instant>>,
+    tag_cache: TagCache,
+}
+
+fn host(reference: &Reference) -> &str {
+    normalize_registry(reference.resolve_registry())
+}
+
+fn client_key(registry: &str, auth: &RegistryAuth) -> String {
+    match auth {
+        RegistryAuth::Anonymous => registry.to_string(),
+        RegistryAuth::Basic(username, password) => format!("{registry}|{username}:{password}"),
+        RegistryAuth::Bearer(token) => format!("{registry}|{token}"),
+    }
+}
+
+fn http_client(settings: &Settings) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = settings.find_proxy(DOCKER_HUB) {
+        let no_proxy = NoProxy::from_string(&proxy.no_proxy.join(","));
+        if let Some(https_proxy) = &proxy.https_proxy {
+            builder = builder.proxy(reqwest::Proxy::https(https_proxy)?.no_proxy(no_proxy.clone()));
+        }
+        if let Some(http_proxy) = &proxy.http_proxy {
+            builder = builder.proxy(reqwest::Proxy::http(http_proxy)?.no_proxy(no_proxy));
+        }
+    }
+    Ok(builder.build()?)
+}
+
+impl Registry {
+    pub async fn new(settings: Settings, clusters: Clusters) -> Result<Registry> {
+        let credentials = Credentials::new(settings.clone(), clusters);
+        let tag_cache = TagCache::new(settings.update_interval);
+        let http = http_client(&settings)?;
+        Ok(Registry {
+            settings,
+            credentials,
+            http,
+            clients: Mutex::new(HashMap::new()),
+            limits: Mutex::new(HashMap::new()),
+            backoff: Mutex::new(HashMap::new()),
+            tag_cache,
+        })
+    }
+
+    pub async fn acquire(&self, image: &Image) -> Result<OwnedSemaphorePermit> {
+        let reference = image.image_id.parse::<Reference>()?;
+        let limit = {
+            let mut limits = self.limits.lock().unwrap_or_else(|e| e.into_inner());
+            limits
+                .entry(reference.resolve_registry().to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.settings.registry_concurrency)))
+                .clone()
+        };
+        let permit = limit.acquire_owned().await?;
+        Ok(permit)
+    }
+
+    fn backing_off(&self, registry: &str) -> bool {
+        let backoff = self.backoff.lock().unwrap_or_else(|e| e.into_inner());
+        backoff
+            .get(registry)
+            .is_some_and(|until| *until > Instant::now())
+    }
+
+    fn back_off(&self, registry: &str) {
+        warn!("Rate limit exceeded for {registry}. Deferring checks.");
+        let mut backoff = self.backoff.lock().unwrap_or_else(|e| e.into_inner());
+        backoff.insert(
+            registry.to_string(),
+            Instant::now() + self.settings.rate_limit_backoff,
+        );
+    }
+
+    pub fn rate_limited(&self, image: &Image) -> bool {
+        match image.image_id.parse::<Reference>() {
+            Ok(reference) => self
+                .endpoints(&reference)
+                .iter()
+                .all(|endpoint| self.backing_off(host(endpoint))),
+            Err(_) => false,
+        }
+    }
+
+    pub async fn docker_hub_rate_limit(&self) -> Result<RateLimit> {
+        let auth = self.credentials.host_auth(DOCKER_HUB).await;
+        let rate_limit = rate_limit::docker_hub_rate_limit(&self.http, &auth).await?;
+        if rate_limit.remaining == Some(0) {
+            self.back_off(DOCKER_HUB);
+        }
+        Ok(rate_limit)
+    }
+
+    fn endpoints(&self, reference: &Reference) -> Vec<Reference> {
+        let registry = reference.registry();
+        let mut endpoints: Vec<Reference> = self
+            .settings
+            .find_mirror(registry)
+            .iter()
+            .flat_map(|m| m.endpoints.iter())
+            .map(|endpoint| {
+                let mut endpoint_reference = reference.clone();
+                if endpoint != registry {
+                    endpoint_reference.set_mirror_registry(endpoint.to_string());
+                }
+                endpoint_reference
+            })
+            .collect();
+        if !endpoints.contains(reference) {
+            endpoints.push(reference.clone());
+        }
+        endpoints
+    }
+
+    async fn client(&self, image: &Image, reference: &Reference) -> Result<(Client, RegistryAuth)> {
+        if self.backing_off(host(reference)) {
+            return Err(anyhow!("Rate limit exceeded for {}", host(reference)));
+        }
+        let auth = self.credentials.registry_auth(image, reference).await;
+        let key = client_key(reference.resolve_registry(), &auth);
+        if let Some(client) = self
+            .clients
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+        {
+            return Ok((client.clone(), auth));
+        }
+        let client = Client::try_from(self.client_config(host(reference))?)?;
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        let client = clients.entry(key).or_insert(client).clone();
+        Ok((client, auth))
+    }
+
+    fn client_config(&self, host: &str) -> Result<ClientConfig> {
+        let mut client_config = ClientConfig::default();
+        if let Some(registry) = self.settings.find_registry(host) {
+            if registry.http == Some(true) {
+                client_config.protocol = ClientProtocol::Http;
+            }
+            client_config.accept_invalid_certificates = registry.insecure_skip_verify == Some(true);
+            if let Some(ca_file) = &registry.ca_file {
+                client_config.extra_root_certificates.push(Certificate {
+                    encoding: CertificateEncoding::Pem,
+                    data: std::fs::read(ca_file)?,
+                });
+            }
+        }
+        if let Some(proxy) = self.settings.find_proxy(host) {
+            client_config.https_proxy = proxy.https_proxy.clone();
+            client_config.http_proxy = proxy.http_proxy.clone();
+            client_config.no_proxy = Some(proxy.no_proxy.join(","));
+        }
+        Ok(client_config)
+    }
+
+    fn check<T>(&self, reference: &Reference, result: oci_client::errors::Result<T>) -> Result<T> {
+        match result {
+            Err(e) if is_rate_limited(&e) => {
+                self.back_off(host(reference));
+                Err(e.into())
+            }
+            result => Ok(result?),
+        }
+    }
+
+    async fn request<T, F, R>(&self, image: &Image, reference: &Reference, request: R) -> Result<T>
+    where
+        F: Future<Output = oci_client::errors::Result<T>>,
+        R: Fn(Client, RegistryAuth, Reference) -> F,
+    {
+        let mut error = anyhow!("No endpoint available for {}", reference.registry());
+        for endpoint in self.endpoints(reference) {
+            let result = match self.client(image, &endpoint).await {
+                Ok((client, auth)) => {
+                    self.check(&endpoint, request(client, auth, endpoint.clone()).await)
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    debug!("Request to {} failed: {e}", host(&endpoint));
+                    error = e;
+                }
+            }
+        }
+        Err(error)
+    }
+
+    pub async fn fetch_manifest_digest(
+        &self,
+        image: &Image,
+        reference: &Reference,
+    ) -> Result<String> {
+        self.request(image, reference, |client, auth, reference| async move {
+            client.fetch_manifest_digest(&reference, &auth).await
+        })
+        .await
+    }
+
+    pub async fn pull_manifest(
+        &self,
+        image: &Image,
+        reference: &Reference,
+    ) -> Result<(OciManifest, String)> {
+        self.request(image, reference, |client, auth, reference| async move {
+            client.pull_manifest(&reference, &auth).await
+        })
+        .await
+    }
+
+    pub async fn pull_config(&self, image: &Image, reference: &Reference) -> Result<String> {
+        self.request(image, reference, |client, auth, reference| async move {
+            client
+                .pull_manifest_and_config(&reference, &auth)
+                .await
+                .map(|(_, _, config)| config)
+        })
+        .await
+    }
+
+    pub async fn list_tags(
+        &self,
+        image: &Image,
+        reference: &Reference,
+        last: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let tags = self
+            .request(image, reference, |client, auth, reference| async move {
+                client.list_tags(&reference, &auth, None, last).await
+            })
+            .await?;
+        Ok(tags.tags)
+    }
+
+    pub async fn cached_tags<F: Future<Output = Result<Vec<String>>>>(
+        &self,
+        reference: &Reference,
+        fetch: F,
+    ) -> Result<Vec<String>> {
+        self.tag_cache.get_or_fetch(reference, fetch).await
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::Result;
+use oci_client::Reference;
+
+use crate::database::Image;
+
+use super::{
+    image_labels::{ImageLabels, REVISION_LABEL, SOURCE_LABEL},
+    registry::Registry,
+};
+
+#[derive(Default)]
+pub struct Source {
+    pub source: Option<String>,
+    pub revision: Option<String>,
+    pub latest_revision: Option<String>,
+    pub compare_url: Option<String>,
+}
+
+pub trait ImageSource {
+    async fn image_source(&self, registry: &Registry) -> Result<Source>;
+}
+
+fn repository_url(source: &str) -> String {
+    let source = source.trim_start_matches("git+").trim_end_matches('/');
+    let source = source.strip_suffix(".git").unwrap_or(source);
+    match source.strip_prefix("git@") {
+        Some(ssh) => format!("https://{}", ssh.replacen(':', "/", 1)),
+        None => source.to_string(),
+    }
+}
+
+fn compare_url(source: &str, revision: &str, latest_revision: &str) -> Option<String> {
+    if revision == latest_revision {
+        return None;
+    }
+    let url = repository_url(source);
+    if url.starts_with("https://github.com/") {
+        Some(format!("{url}/compare/{revision}...{latest_revision}"))
+    } else if url.contains("gitlab") {
+        Some(format!("{url}/-/compare/{revision}...{latest_revision}"))
+    } else {
+        None
+    }
+}
+
+impl ImageSource for Image {
+    async fn image_source(&self, registry: &Registry) -> Result<Source> {
+        let (Some(resolved_image_id), Some(latest_image_id)) =
+            (&self.resolved_image_id, &self.latest_image_id)
+        else {
+            return Ok(Source::default());
+        };
+        let labels = self
+            .image_labels(registry, &resolved_image_id.parse::<Reference>()?)
+            .await?;
+        let latest_labels = self
+            .image_labels(registry, &latest_image_id.parse::<Reference>()?)
+            .await?;
+
+        let source = latest_labels
+            .get(SOURCE_LABEL)
+            .or(labels.get(SOURCE_LABEL))
+            .cloned();
+        let revision = labels.get(REVISION_LABEL).cloned();
+        let latest_revision = latest_labels.get(REVISION_LABEL).cloned();
+        let compare_url = match (&source, &revision, &latest_revision) {
+            (Some(source), Some(revision), Some(latest_revision)) => {
+                compare_url(source, revision, latest_revision)
+            }
+            _ => None,
+        };
+        Ok(Source {
+            source,
+            revision,
+            latest_revision,
+            compare_url,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_url() {
+        assert_eq!(
+            compare_url("https://github.com/nginx/nginx.git", "a1", "b2").as_deref(),
+            Some("https://github.com/nginx/nginx/compare/a1...b2")
+        );
+        assert_eq!(
+            compare_url("git@gitlab.com:group/project.git", "a1", "b2").as_deref(),
+            Some("https://gitlab.com/group/project/-/compare/a1...b2")
+        );
+        assert_eq!(
+            compare_url("https://github.com/nginx/nginx", "a1", "a1"),
+            None
+        );
+        assert_eq!(compare_url("https://example.com/repo", "a1", "b2"), None);
+    }
+}
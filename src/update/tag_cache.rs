@@ -0,0 +1,91 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use oci_client::Reference;
+use tokio::time::Instant;
+
+type Entry = Arc<tokio::sync::Mutex<Option<(Instant, Vec<String>)>>>;
+
+pub struct TagCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl TagCache {
+    pub fn new(ttl: Duration) -> TagCache {
+        TagCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn entry(&self, reference: &Reference) -> Entry {
+        let key = format!("{}/{}", reference.registry(), reference.repository());
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| match entry.try_lock() {
+            Ok(entry) => entry.as_ref().is_some_and(|(t, _)| t.elapsed() < self.ttl),
+            Err(_) => true,
+        });
+        entries.entry(key).or_default().clone()
+    }
+
+    pub async fn get_or_fetch<F: Future<Output = Result<Vec<String>>>>(
+        &self,
+        reference: &Reference,
+        fetch: F,
+    ) -> Result<Vec<String>> {
+        let entry = self.entry(reference);
+        let mut entry = entry.lock().await;
+        match &*entry {
+            Some((fetched, tags)) if fetched.elapsed() < self.ttl => Ok(tags.clone()),
+            _ => {
+                let tags = fetch.await?;
+                if !tags.is_empty() {
+                    *entry = Some((Instant::now(), tags.clone()));
+                }
+                Ok(tags)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_once() -> Result<()> {
+        let cache = TagCache::new(Duration::from_secs(60));
+        let nginx = "nginx:1.29".parse::<Reference>()?;
+        let nginx_alpine = "docker.io/library/nginx:1.29-alpine".parse::<Reference>()?;
+        let tags = cache
+            .get_or_fetch(&nginx, async { Ok(vec!["1.29".to_string()]) })
+            .await?;
+        assert_eq!(tags, vec!["1.29"]);
+        let tags = cache
+            .get_or_fetch(&nginx_alpine, async { Ok(vec!["1.30".to_string()]) })
+            .await?;
+        assert_eq!(tags, vec!["1.29"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::Result;
+
+use crate::database::Image;
+
+use super::{
+    latest_image_version::{VersionParser, captured_version},
+    version::ImageVersion,
+};
+
+pub trait UpdateKind {
+    fn update_kind(&self) -> Result<Option<String>>;
+}
+
+impl UpdateKind for Image {
+    fn update_kind(&self) -> Result<Option<String>> {
+        if self.latest_image_id.is_some()
+            && (self.latest_image_id == self.resolved_image_id
+                || self.latest_image_id.as_ref() == Some(&self.image_id))
+        {
+            return Ok(None);
+        }
+
+        let version_regex = self.version_regex()?;
+        let vp = VersionParser::for_image(self)?;
+        let parse = |v: &Option<String>| {
+            v.as_ref()
+                .and_then(|v| captured_version(&version_regex, v))
+                .and_then(|(_, v)| vp.parse_version(&v))
+                .and_then(|v| v.semver())
+        };
+
+        let update_kind = match (parse(&self.version), parse(&self.latest_version)) {
+            (Some(version), Some(latest_version)) if latest_version > version => {
+                if latest_version.major != version.major {
+                    "major"
+                } else if latest_version.minor != version.minor {
+                    "minor"
+                } else if latest_version.patch != version.patch {
+                    "patch"
+                } else {
+                    "prerelease"
+                }
+            }
+            (Some(version), Some(latest_version)) if latest_version < version => return Ok(None),
+            _ if self.latest_image_id.is_none() => return Ok(None),
+            _ => "digest",
+        };
+        Ok(Some(update_kind.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(version: &str, latest_version: &str) -> Image {
+        Image {
+            resolved_image_id: Some("docker.io/library/nginx@sha256:1".to_string()),
+            latest_image_id: Some("docker.io/library/nginx@sha256:2".to_string()),
+            version: Some(version.to_string()),
+            latest_version: Some(latest_version.to_string()),
+            ..Image::for_test(
+                &format!("nginx:{version}"),
+                "docker.io/library/nginx@sha256:1",
+            )
+        }
+    }
+
+    #[test]
+    fn test_update_kind() -> Result<()> {
+        assert_eq!(
+            image("1.2.3", "2.0.0").update_kind()?.as_deref(),
+            Some("major")
+        );
+        assert_eq!(
+            image("1.2.3", "1.3.0").update_kind()?.as_deref(),
+            Some("minor")
+        );
+        assert_eq!(
+            image("1.2.3", "1.2.4").update_kind()?.as_deref(),
+            Some("patch")
+        );
+        assert_eq!(
+            image("1.2.3-rc1", "1.2.3").update_kind()?.as_deref(),
+            Some("prerelease")
+        );
+        assert_eq!(
+            image("1.2.3", "1.2.3").update_kind()?.as_deref(),
+            Some("digest")
+        );
+        assert_eq!(image("1.2.4", "1.2.3").update_kind()?, None);
+        let up_to_date = Image {
+            latest_image_id: Some("docker.io/library/nginx@sha256:1".to_string()),
+            ..image("1.2.3", "1.2.3")
+        };
+        assert_eq!(up_to_date.update_kind()?, None);
+        assert_eq!(
+            image("latest", "1.2.3").update_kind()?.as_deref(),
+            Some("digest")
+        );
+        Ok(())
+    }
+}
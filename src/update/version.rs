@@ -15,18 +15,93 @@
  */
 
 use oci_client::Reference;
+use regex::Regex;
 
 use crate::database::Image;
 use anyhow::Result;
 
+use super::latest_image_version::{VersionParser, captured_version};
+
+const AUTO_VERSION_REGEX: &str = "auto";
+
 pub trait ImageVersion {
     fn image_version(&self) -> Result<Option<String>>;
+    fn version_regex(&self) -> Result<Regex>;
+    fn is_version(&self, tag: &str) -> Result<bool>;
+}
+
+/// Derives a regex matching tags of the same shape as the given tag: same prefix,
+/// same number of version components and the same suffix (ignoring numbers).
+fn infer_version_regex(tag: &str) -> Option<String> {
+    let shape = Regex::new(r"^(?<prefix>\D*?)(?<version>\d+(?:\.\d+)*)(?<suffix>.*)$").ok()?;
+    let caps = shape.captures(tag)?;
+    let components = caps["version"].split('.').count();
+    let suffix = Regex::new(r"\d+")
+        .ok()?
+        .split(&caps["suffix"])
+        .map(regex::escape)
+        .collect::<Vec<String>>()
+        .join(r"\d+");
+    Some(format!(
+        r"^{}(\d+{}){suffix}$",
+        regex::escape(&caps["prefix"]),
+        r"\.\d+".repeat(components - 1),
+    ))
 }
 
 impl ImageVersion for Image {
     fn image_version(&self) -> Result<Option<String>> {
         let reference = self.image.parse::<Reference>()?;
-        let version = reference.tag().map(|tag| tag.to_string());
+        let version = reference
+            .tag()
+            .map(|tag| tag.to_string())
+            .or(self.discovered_tag.clone());
         Ok(version)
     }
+
+    fn version_regex(&self) -> Result<Regex> {
+        if self.latest_version_regex != AUTO_VERSION_REGEX {
+            return Ok(Regex::new(&self.latest_version_regex)?);
+        }
+        let version_regex = self
+            .image_version()?
+            .and_then(|tag| infer_version_regex(&tag))
+            .unwrap_or(".*".to_string());
+        Ok(Regex::new(&version_regex)?)
+    }
+
+    fn is_version(&self, tag: &str) -> Result<bool> {
+        let vp = VersionParser::for_image(self)?;
+        let is_version = captured_version(&self.version_regex()?, tag)
+            .and_then(|(_, v)| vp.parse_version(&v))
+            .is_some();
+        Ok(is_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_version_regex() -> Result<()> {
+        let matches = |tag: &str, candidate: &str| -> Result<Option<String>> {
+            let version_regex = Regex::new(&infer_version_regex(tag).unwrap_or_default())?;
+            Ok(version_regex.captures(candidate).map(|c| c[1].to_string()))
+        };
+        assert_eq!(
+            matches("1.25.3-alpine", "1.27.0-alpine")?.as_deref(),
+            Some("1.27.0")
+        );
+        assert_eq!(matches("1.25.3-alpine", "1.27.0-bookworm")?, None);
+        assert_eq!(matches("1.25.3-alpine", "1.27-alpine")?, None);
+        assert_eq!(matches("v2.1", "v2.2")?.as_deref(), Some("2.2"));
+        assert_eq!(matches("v2.1", "2.2")?, None);
+        assert_eq!(
+            matches("3.12-alpine3.19", "3.13-alpine3.20")?.as_deref(),
+            Some("3.13")
+        );
+        assert_eq!(infer_version_regex("latest"), None);
+        Ok(())
+    }
 }
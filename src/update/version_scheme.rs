@@ -0,0 +1,78 @@
+/*
+ * Copyright 2025 Michael Krolikowski
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{fmt, str::FromStr};
+
+use semver::Version;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionScheme {
+    Semver,
+    Calver,
+    Date,
+    Lexical,
+    Numeric,
+}
+
+#[derive(Debug)]
+pub struct UnknownVersionScheme(String);
+
+impl fmt::Display for UnknownVersionScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown version scheme: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownVersionScheme {}
+
+impl FromStr for VersionScheme {
+    type Err = UnknownVersionScheme;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "semver" => Ok(VersionScheme::Semver),
+            "calver" => Ok(VersionScheme::Calver),
+            "date" => Ok(VersionScheme::Date),
+            "lexical" => Ok(VersionScheme::Lexical),
+            "numeric" => Ok(VersionScheme::Numeric),
+            s => Err(UnknownVersionScheme(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ParsedVersion {
+    Semver(Version),
+    Components(Vec<u64>),
+    Lexical(String),
+}
+
+impl ParsedVersion {
+    /// Semver representation used to match version requirements and to classify updates.
+    /// Components are mapped the same way as partial semver versions.
+    pub fn semver(&self) -> Option<Version> {
+        match self {
+            ParsedVersion::Semver(v) => Some(v.clone()),
+            ParsedVersion::Components(c) => match c.as_slice() {
+                [] => None,
+                [patch] => Some(Version::new(0, 0, *patch)),
+                [major, minor] => Some(Version::new(*major, *minor, 0)),
+                [major, minor, patch, ..] => Some(Version::new(*major, *minor, *patch)),
+            },
+            ParsedVersion::Lexical(_) => None,
+        }
+    }
+}